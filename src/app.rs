@@ -1,48 +1,179 @@
-use serde_json::Value;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
 use tui::widgets::TableState;
 
 use crate::{
-    filter::Filter,
-    keymap::{Event, KeyMode, Keymap},
-    utils::{filter, get_email_list, himalaya_command},
+    cache::Cache,
+    clipboard,
+    compose::{Draft, FilePicker},
+    config::Config,
+    export,
+    filter::{Filter, COMMANDS},
+    input::LineEditor,
+    keymap::{Event, KeyMode, Keymap, YankField},
+    links::LinkPicker,
+    mail::{self, BackendKind, MailBackend, QUERY_WORDS},
+    message::Message,
+    palette::{Action, Palette},
+    pipe::{Pipe, PipeOutput},
+    utils::{
+        get_reply_template, get_template, private_temp_dir, send_message, watch_command,
+        SEARCH_KEYWORDS,
+    },
+    version::Version,
+    watch::{WatchEvent, Watcher},
 };
 
-use self::data::{Msg, Response, ReviewFlags};
+use self::data::{Flag, Msg, ReviewFlags};
 
 #[derive(Debug)]
 pub struct App {
     pub emails: Vec<Msg>,
+    pub config: Config,
     pub keymap: Keymap,
-    pub command_input: String,
+    pub command_input: LineEditor,
+    /// Text searched in the message being read.
+    pub find_input: LineEditor,
     pub filters: Vec<Filter>,
     pub state: AppState,
     pub should_quit: bool,
     pub need_update: bool,
+    /// Feedback of the last action, shown in the command line until next key.
+    pub info: Option<String>,
+    pub draft: Option<Draft>,
+    pub picker: Option<FilePicker>,
+    pub palette: Option<Palette>,
+    pub link_picker: Option<LinkPicker>,
+    /// Text yanked from the app, pasted in the command line.
+    pub register: Option<String>,
+    /// Mode whose keybindings are shown in the help, with the scroll offset.
+    pub help: Option<(KeyMode, u16)>,
+    /// Interactive program to run with the terminal restored, like an editor.
+    pub interactive: Option<Command>,
+    pub cache: Cache,
+    /// Whether himalaya could not be reached on the last request.
+    pub offline: bool,
+    /// Envelopes being fetched in background.
+    sync: Option<Receiver<Result<Vec<Msg>, String>>>,
+    last_poll: Instant,
+    /// Process pushing changes of the folder, polling is used when there is none.
+    watcher: Option<Watcher>,
+    /// Whether the folder changed during the current sync.
+    resync: bool,
+    /// Number of unseen envelopes arrived since the last message was read.
+    pub new_mail: usize,
+    pub last_sync: Option<Instant>,
+    /// Number of ticks since the start, used to animate the status.
+    pub ticks: usize,
+    /// Version of himalaya, unknown if it cannot be run.
+    pub version: Option<Version>,
+    /// Folders of the account, fetched on first completion.
+    folders: Option<Vec<String>>,
+    /// Backend reading the folder of the configuration.
+    pub mail: Arc<dyn MailBackend>,
+    /// Envelopes of the messages marked for export, compared with `Msg::same` since ids
+    /// may be given to other messages by a sync.
+    pub marked: Vec<Msg>,
+    /// Envelopes of the folder at the last sync, whatever the filter, to tell new mail.
+    known: Vec<Msg>,
+    /// Output of the last command the message was piped to, shown until closed.
+    pub pipe_output: Option<PipeOutput>,
+    /// Private directory of the files given to other programs, removed on exit.
+    temp_dir: Option<PathBuf>,
 }
 
 impl App {
-    pub fn new() -> Self {
-        let emails: Vec<Msg> = serde_json::from_str::<Response>(&get_email_list())
-            .unwrap()
-            .response;
+    pub fn new(config: Config) -> Self {
+        // show cached envelopes until the server answers
+        let cache = Cache::new(&config);
+        let emails = cache.load_envelopes().unwrap_or_default();
+        // himalaya is not needed to read a maildir
+        let version = match config.backend {
+            BackendKind::Himalaya => Version::detect(&config),
+            _ => None,
+        };
+        let mail = mail::open(&config, version);
 
-        App {
+        let mut app = App {
+            known: emails.clone(),
             emails,
+            command_input: LineEditor::with_history(config.history_file.clone()),
+            config,
             keymap: Keymap::default_keymap(),
             state: AppState {
                 msg_table: TableState::default(),
                 content: (String::new(), 0),
+                message: None,
                 review_flags: ReviewFlags::default(),
             },
-            command_input: String::new(),
+            find_input: LineEditor::default(),
             filters: Vec::new(),
             should_quit: false,
             need_update: false,
+            info: None,
+            draft: None,
+            picker: None,
+            palette: None,
+            help: None,
+            link_picker: None,
+            register: None,
+            interactive: None,
+            cache,
+            offline: false,
+            sync: None,
+            last_poll: Instant::now(),
+            watcher: None,
+            resync: false,
+            new_mail: 0,
+            last_sync: None,
+            ticks: 0,
+            version,
+            folders: None,
+            mail,
+            marked: Vec::new(),
+            pipe_output: None,
+            temp_dir: None,
+        };
+        if app.config.idle {
+            match Watcher::spawn(watch_command(&app.config, app.version)) {
+                Ok(watcher) => app.watcher = Some(watcher),
+                Err(e) => app.info = Some(format!("Cannot watch folder, polling: {}", e)),
+            }
         }
+        app.sync();
+        // a single message is opened right away
+        if app.config.backend == BackendKind::Eml {
+            // syncs do not update filtered lists, and the file is at hand
+            app.emails = app.mail.envelopes().unwrap_or_default();
+            app.filters.push(Filter("read 1".to_string()));
+            app.need_update = true;
+        }
+        app
     }
 
     /// Processing application event.
     pub fn on_event(&mut self, event: Event) {
+        self.info = None;
+        if event == Event::OpenPalette {
+            self.palette = Some(Palette::new(&self.keymap, &self.keymap.mode));
+            self.keymap.switch_to(KeyMode::Palette);
+            return;
+        }
+        if event == Event::ShowHelp {
+            self.help = Some((self.keymap.mode.clone(), 0));
+            self.keymap.switch_to(KeyMode::Help);
+            return;
+        }
         match self.keymap.mode {
             // Process keybind on move mode.
             KeyMode::Motion => match event {
@@ -55,13 +186,41 @@ impl App {
                     self.need_update = true; // Update needed
                     self.command_input.clear();
                 }
+                Event::Compose => {
+                    let template = get_template(&self.config, self.version);
+                    self.open_draft(&template);
+                }
                 Event::ReviewMsg => {
+                    self.new_mail = 0;
                     if let Some(selected) = self.state.msg_table.selected() {
                         let id = self.emails[selected].id;
                         self.filters.push(Filter(format!("read {}", id)));
                         self.need_update = true;
                     }
                 }
+                Event::ToggleSeen => self.toggle_flag(Flag::Seen),
+                Event::ToggleFlagged => self.toggle_flag(Flag::Flagged),
+                Event::ExportMessage => self.export_message(),
+                Event::PipeMessage => {
+                    self.command_input.set("pipe ");
+                    self.keymap.switch_to(KeyMode::Insert);
+                }
+                Event::ToggleMark => {
+                    if let Some(msg) = self
+                        .state
+                        .msg_table
+                        .selected()
+                        .and_then(|i| self.emails.get(i))
+                    {
+                        match self.marked.iter().position(|m| m.same(msg)) {
+                            Some(index) => {
+                                self.marked.remove(index);
+                            }
+                            None => self.marked.push(msg.clone()),
+                        }
+                        self.state.next(self.emails.len());
+                    }
+                }
                 _ => {}
             },
             // Process keybind on input mode.
@@ -71,44 +230,371 @@ impl App {
                     self.keymap.switch_back();
                 }
                 Event::Submit => {
-                    self.filters.push(Filter(self.command_input.clone()));
+                    if let Err(e) = self.command_input.commit() {
+                        self.info = Some(format!("Cannot save history: {}", e));
+                    }
+                    self.filters
+                        .push(Filter(self.command_input.text().trim().to_string()));
                     self.keymap.switch_back();
                     self.need_update = true;
                 }
-                Event::RawInput(c) => self.command_input.push(c),
-                Event::Backspace => {
-                    self.command_input.pop();
+                Event::RawInput(c) => self.command_input.insert(c),
+                Event::Backspace => self.command_input.backspace(),
+                Event::Delete => self.command_input.delete(),
+                Event::CursorLeft => self.command_input.left(),
+                Event::CursorRight => self.command_input.right(),
+                Event::CursorStart => self.command_input.home(),
+                Event::CursorEnd => self.command_input.end(),
+                Event::DeleteWord => self.command_input.delete_word(),
+                Event::DeleteToStart => self.command_input.delete_to_start(),
+                Event::HistoryPrev => self.command_input.history_prev(),
+                Event::HistoryNext => self.command_input.history_next(),
+                Event::Paste => {
+                    if let Some(text) = &self.register {
+                        for c in text.chars() {
+                            self.command_input.insert(c);
+                        }
+                    }
+                }
+                Event::Complete => {
+                    let candidates = self.completions();
+                    self.command_input.complete(candidates);
                 }
                 _ => {}
             },
             // Process keybind on read mode.
             KeyMode::Review => match event {
+                Event::Quit if self.config.backend == BackendKind::Eml => self.should_quit = true,
                 Event::Quit => {
                     self.keymap.switch_to(KeyMode::Motion);
                     self.state.content = (String::new(), 0);
+                    self.state.message = None;
+                    self.state.review_flags.search = None;
                     self.filters.pop();
                     self.need_update = true;
                     self.command_input.clear();
                 }
-                Event::ScrollUp => {
-                    if self.state.content.1 > 0 {
-                        self.state.content.1 -= 1;
+                Event::ScrollUp => self.state.content.1 = self.state.content.1.saturating_sub(1),
+                Event::ScrollDown => self.state.content.1 += 1,
+                Event::SwitchMode(mode) => self.keymap.switch_to(mode),
+                Event::StartSearch => {
+                    self.find_input.clear();
+                    self.keymap.switch_to(KeyMode::Find);
+                }
+                Event::NextMatch | Event::PrevMatch => {
+                    let flags = &mut self.state.review_flags;
+                    let size = flags.matches.len();
+                    if size == 0 {
+                        self.info = Some("No match".to_string());
+                    } else {
+                        flags.current_match = match event {
+                            Event::NextMatch => (flags.current_match + 1) % size,
+                            _ => (flags.current_match + size - 1) % size,
+                        };
+                        flags.jump = true;
+                    }
+                }
+                Event::StartVisual => {
+                    let flags = &mut self.state.review_flags;
+                    // start from the first line shown
+                    let top = flags
+                        .line_rows
+                        .iter()
+                        .rposition(|row| *row <= self.state.content.1 as usize)
+                        .unwrap_or(0);
+                    if !flags.lines.is_empty() {
+                        flags.visual = Some((top, top));
+                        self.keymap.switch_to(KeyMode::Visual);
                     }
                 }
-                Event::ScrollDown => self.state.content.1 += 1,
                 Event::ShowLinks => {
-                    self.state.review_flags.show_links = true;
-                    self.command_input.clear();
-                    self.command_input = "follow ".to_string();
-                    self.keymap.switch_to(KeyMode::Insert);
+                    if self.state.review_flags.links.is_empty() {
+                        self.info = Some("No link in this message".to_string());
+                    } else {
+                        self.state.review_flags.show_links = true;
+                        self.link_picker =
+                            Some(LinkPicker::new(self.state.review_flags.links.clone()));
+                        self.keymap.switch_to(KeyMode::Links);
+                    }
                 }
                 Event::ShowStats => self.state.review_flags.show_stats ^= true,
+                Event::ExportMessage => self.export_message(),
+                Event::PipeMessage => {
+                    self.command_input.set("pipe ");
+                    self.keymap.switch_to(KeyMode::Insert);
+                }
+                Event::ToggleHeaders => self.state.review_flags.full_headers ^= true,
+                Event::ToggleSource => self.state.review_flags.raw_source ^= true,
+                Event::ToggleFold => self.state.review_flags.fold ^= true,
+                Event::NextAttachment => {
+                    if let Some(message) = &self.state.message {
+                        if !message.attachments.is_empty() {
+                            let flags = &mut self.state.review_flags;
+                            flags.attachment = (flags.attachment + 1) % message.attachments.len();
+                        }
+                    }
+                }
+                Event::SaveAttachment => self.save_attachments(false),
+                Event::SaveAllAttachments => self.save_attachments(true),
+                Event::OpenAttachment => self.open_attachment(),
+                Event::TogglePart => {
+                    let part = self.state.review_flags.part.toggle();
+                    if let Some(message) = &self.state.message {
+                        if message.has_part(&part) {
+                            self.state.review_flags.part = part;
+                            self.state.show_body();
+                        }
+                    }
+                }
+                _ => {}
+            },
+            // Process keybind on compose confirmation.
+            KeyMode::Compose => match event {
+                Event::Quit => self.close_draft(),
+                Event::EditDraft => {
+                    if let Some(draft) = &self.draft {
+                        self.interactive = Some(editor_command(&draft.path));
+                    }
+                }
+                Event::AttachFile => {
+                    let dir = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir());
+                    self.picker = Some(FilePicker::new(dir));
+                    self.keymap.switch_to(KeyMode::Picker);
+                }
+                Event::RemoveAttachment => {
+                    if let Some(draft) = &mut self.draft {
+                        draft.remove_selected();
+                    }
+                }
+                Event::NextAttachment => {
+                    if let Some(draft) = &mut self.draft {
+                        if !draft.attachments.is_empty() {
+                            draft.selected = (draft.selected + 1) % draft.attachments.len();
+                        }
+                    }
+                }
+                Event::PrevAttachment => {
+                    if let Some(draft) = &mut self.draft {
+                        let size = draft.attachments.len();
+                        if size != 0 {
+                            draft.selected = (draft.selected + size - 1) % size;
+                        }
+                    }
+                }
+                Event::SendDraft => self.send_draft(),
                 _ => {}
             },
+            // Process keybind on file picker.
+            KeyMode::Picker => {
+                if let Some(picker) = &mut self.picker {
+                    match event {
+                        Event::Quit => {
+                            self.picker = None;
+                            self.keymap.switch_back();
+                        }
+                        Event::SelectNext => picker.next(),
+                        Event::SelectPrev => picker.previous(),
+                        Event::RawInput(c) => picker.push(c),
+                        Event::Backspace => picker.pop(),
+                        Event::Submit => {
+                            if let Some(path) = picker.enter() {
+                                if let Some(draft) = &mut self.draft {
+                                    draft.attachments.push(path);
+                                    draft.selected = draft.attachments.len() - 1;
+                                }
+                                self.picker = None;
+                                self.keymap.switch_back();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Process keybind on link picker.
+            KeyMode::Links => {
+                if let Some(picker) = &mut self.link_picker {
+                    match event {
+                        Event::Quit => {
+                            self.link_picker = None;
+                            self.state.review_flags.show_links = false;
+                            self.keymap.switch_back();
+                        }
+                        Event::SelectNext => picker.next(),
+                        Event::SelectPrev => picker.previous(),
+                        Event::RawInput(c) if LinkPicker::is_hint(c) => picker.push(c),
+                        Event::Backspace => picker.pop(),
+                        Event::OpenLink => {
+                            if let Some(link) = picker.selected() {
+                                self.info = Some(match open::that(link) {
+                                    Ok(()) => format!("Opened {}", link),
+                                    Err(e) => format!("Cannot open link: {}", e),
+                                });
+                            }
+                        }
+                        Event::CopyLink => {
+                            if let Some(link) = picker.selected() {
+                                self.info = Some(
+                                    match clipboard::copy(link, &self.config.clipboard_command) {
+                                        Ok(()) => format!("Copied {}", link),
+                                        Err(e) => format!("Cannot copy link: {}", e),
+                                    },
+                                );
+                            }
+                        }
+                        Event::YankLink => {
+                            if let Some(link) = picker.selected() {
+                                self.info = Some(format!("Yanked {}", link));
+                                self.register = Some(link.to_string());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Process keybind on search in the message.
+            KeyMode::Find => match event {
+                Event::Quit => self.keymap.switch_back(),
+                Event::Submit => {
+                    let flags = &mut self.state.review_flags;
+                    let text = self.find_input.text();
+                    flags.search = (!text.is_empty()).then(|| text.to_string());
+                    flags.current_match = 0;
+                    flags.jump = true;
+                    self.keymap.switch_back();
+                }
+                Event::RawInput(c) => self.find_input.insert(c),
+                Event::Backspace => self.find_input.backspace(),
+                Event::Delete => self.find_input.delete(),
+                Event::CursorLeft => self.find_input.left(),
+                Event::CursorRight => self.find_input.right(),
+                Event::CursorStart => self.find_input.home(),
+                Event::CursorEnd => self.find_input.end(),
+                Event::DeleteWord => self.find_input.delete_word(),
+                Event::DeleteToStart => self.find_input.delete_to_start(),
+                _ => {}
+            },
+            // Process keybind on line selection.
+            KeyMode::Visual => {
+                let flags = &mut self.state.review_flags;
+                match (event, flags.visual) {
+                    (Event::Quit, _) => {
+                        flags.visual = None;
+                        self.keymap.switch_back();
+                    }
+                    (Event::SelectNext, Some((anchor, cursor))) => {
+                        let last = flags.lines.len().saturating_sub(1);
+                        flags.visual = Some((anchor, (cursor + 1).min(last)));
+                        flags.jump = true;
+                    }
+                    (Event::SelectPrev, Some((anchor, cursor))) => {
+                        flags.visual = Some((anchor, cursor.saturating_sub(1)));
+                        flags.jump = true;
+                    }
+                    (Event::YankSelection, Some(_)) => {
+                        let text = self.state.selection().join("\n");
+                        self.info = Some(
+                            match clipboard::copy(&text, &self.config.clipboard_command) {
+                                Ok(()) => format!("Copied {} lines", self.state.selection().len()),
+                                Err(e) => format!("Cannot copy: {}", e),
+                            },
+                        );
+                        self.state.review_flags.visual = None;
+                        self.keymap.switch_back();
+                    }
+                    (Event::QuoteReply, Some(_)) => {
+                        self.state.review_flags.visual = None;
+                        self.keymap.switch_back();
+                        self.quote_reply();
+                    }
+                    _ => {}
+                }
+            }
+            // Process keybind on yank prefix.
+            KeyMode::Yank => {
+                self.keymap.switch_back();
+                match event {
+                    Event::Yank(field) => self.yank(field),
+                    // only messages being read have links
+                    Event::ShowLinks if self.keymap.mode == KeyMode::Review => self.on_event(event),
+                    _ => {}
+                }
+            }
+            // Process keybind on help.
+            KeyMode::Help => {
+                if let Some((mode, scroll)) = &mut self.help {
+                    match event {
+                        Event::Quit => {
+                            self.help = None;
+                            self.keymap.switch_back();
+                        }
+                        Event::ScrollUp => *scroll = scroll.saturating_sub(1),
+                        Event::ScrollDown => {
+                            let size = self.keymap.keybinds(mode).len() as u16;
+                            *scroll = (*scroll + 1).min(size.saturating_sub(1));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Process keybind on the output of a piped command.
+            KeyMode::Output => {
+                if let Some(output) = &mut self.pipe_output {
+                    match event {
+                        Event::Quit => {
+                            self.pipe_output = None;
+                            self.keymap.switch_back();
+                        }
+                        Event::ScrollUp => output.scroll = output.scroll.saturating_sub(1),
+                        Event::ScrollDown => {
+                            let size = output.text.lines().count() as u16;
+                            output.scroll = (output.scroll + 1).min(size.saturating_sub(1));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // Process keybind on command palette.
+            KeyMode::Palette => {
+                if let Some(palette) = &mut self.palette {
+                    match event {
+                        Event::Quit => {
+                            self.palette = None;
+                            self.keymap.switch_back();
+                        }
+                        Event::SelectNext => palette.next(),
+                        Event::SelectPrev => palette.previous(),
+                        Event::RawInput(c) => palette.push(c),
+                        Event::Backspace => palette.pop(),
+                        Event::Submit => {
+                            let action = palette.action();
+                            self.palette = None;
+                            self.keymap.switch_back();
+                            match action {
+                                Some(Action::Event(event)) => self.on_event(event),
+                                Some(Action::Command(name)) => {
+                                    self.command_input.set(&format!("{} ", name));
+                                    self.keymap.switch_to(KeyMode::Insert);
+                                }
+                                None => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
         }
     }
 
     pub fn on_tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+        self.poll_watcher();
+        self.poll_sync();
+        if let (None, Some(interval)) = (&self.watcher, self.config.poll_interval()) {
+            if self.sync.is_none() && self.last_poll.elapsed() >= interval {
+                self.sync();
+            }
+        }
+
         let mut command: Vec<String> = Vec::new();
         if let Some(Filter(filter)) = self.curr_filter() {
             command = filter.split(' ').map(|s| s.to_string()).collect();
@@ -117,19 +603,99 @@ impl App {
         if !command.is_empty() {
             if self.need_update {
                 match command[0].to_uppercase().as_str() {
-                    "SEARCH" => {
-                        let output = filter(&self.emails);
-                        self.emails = output;
-                        self.state.msg_table = TableState::default();
+                    "SEARCH" => match self.mail.search(&command[1..]) {
+                        Ok(emails) => {
+                            self.emails = emails;
+                            self.state.msg_table = TableState::default();
+                        }
+                        Err(e) => {
+                            self.info = Some(format!("Cannot search: {}", e));
+                            self.filters.pop();
+                        }
+                    },
+                    "FOLDER" => {
+                        self.filters.pop();
+                        match command.get(1..).map(|words| words.join(" ")) {
+                            Some(folder) if !folder.is_empty() => self.switch_folder(&folder),
+                            _ => self.info = Some("Missing folder name".to_string()),
+                        }
+                        self.command_input.clear();
+                    }
+                    "MOVE" => {
+                        self.filters.pop();
+                        match command.get(1..).map(|words| words.join(" ")) {
+                            Some(folder) if !folder.is_empty() => self.move_selected(&folder),
+                            _ => self.info = Some("Missing folder name".to_string()),
+                        }
+                        self.command_input.clear();
+                    }
+                    "EXPORT" => {
+                        self.filters.pop();
+                        self.command_input.clear();
+                        match (
+                            command.get(1),
+                            command.get(2..).map(|words| words.join(" ")),
+                        ) {
+                            (Some(format), path) => match export::Format::parse(format) {
+                                Some(format) => match path.filter(|path| !path.is_empty()) {
+                                    Some(path) => self.export_messages(format, &path),
+                                    None => {
+                                        // prompt for the path, starting from the downloads
+                                        let dir = self.config.downloads_dir.display().to_string();
+                                        let path = match format {
+                                            export::Format::Mbox => {
+                                                format!("{}/messages.mbox", dir)
+                                            }
+                                            _ => format!("{}/", dir),
+                                        };
+                                        self.command_input.set(&format!(
+                                            "export {} {}",
+                                            format.extension(),
+                                            path
+                                        ));
+                                        self.keymap.switch_to(KeyMode::Insert);
+                                    }
+                                },
+                                None => self.info = Some(format!("Unknown format {}", format)),
+                            },
+                            (None, _) => self.info = Some("Missing format".to_string()),
+                        }
+                    }
+                    "PIPE" => {
+                        self.filters.pop();
+                        self.command_input.clear();
+                        match Pipe::parse(&command[1..]) {
+                            Some(pipe) => self.pipe_message(pipe),
+                            None => self.info = Some("Missing command".to_string()),
+                        }
+                    }
+                    "TAG" => {
+                        self.filters.pop();
+                        self.tag_selected(&command[1..]);
+                        self.command_input.clear();
                     }
                     "READ" => {
-                        // commands that request update from himalaya
-                        let output = himalaya_command(command.clone());
+                        let id = command.get(1).and_then(|id| id.parse::<usize>().ok());
+                        let raw = match id {
+                            Some(id) => self.read_raw(id),
+                            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid id")),
+                        };
 
-                        if let Ok(response) = serde_json::from_str::<Value>(&output) {
-                            let content = response.get("response").unwrap().as_str().unwrap();
-                            self.state.content = (content.to_string(), 0);
-                            self.keymap.switch_to(KeyMode::Review);
+                        match raw.map(|raw| Message::parse(raw.as_bytes())) {
+                            Ok(Ok(message)) => {
+                                self.state.review_flags.attachment = 0;
+                                self.state.message = Some(message);
+                                self.state.show_body();
+                                self.keymap.switch_to(KeyMode::Review);
+                            }
+                            Ok(Err(e)) => {
+                                self.info = Some(format!("Cannot parse message: {}", e));
+                                self.filters.pop();
+                            }
+                            Err(e) => {
+                                self.info = Some(format!("Cannot read message: {}", e));
+                                self.filters.pop();
+                            }
                         }
                     }
                     _ => {}
@@ -138,46 +704,765 @@ impl App {
                 self.need_update = false;
             } else {
                 // process command that does not need to update from himalaya
-                match command[0].to_uppercase().as_str() {
-                    "FOLLOW" => {
-                        // open URL if the argument is correct
-                        if let Ok(index) = command[1].parse::<u16>() {
-                            if index as usize <= self.state.review_flags.links.len() {
-                                open::that(&self.state.review_flags.links[(index - 1) as usize])
-                                    .unwrap();
-                            }
-                        }
-                        // clear command input
-                        self.state.review_flags.show_links = false;
-                        self.keymap.switch_to(KeyMode::Review);
-                        self.filters.pop();
-                        self.command_input.clear();
-                    }
-                    _ => {}
+                if command[0].to_uppercase().as_str() == "FOLLOW" {
+                    // open URL if the argument is correct, links are numbered from 1
+                    let link = command
+                        .get(1)
+                        .and_then(|index| index.parse::<usize>().ok())
+                        .and_then(|index| index.checked_sub(1))
+                        .and_then(|index| self.state.review_flags.links.get(index));
+                    self.info = Some(match link {
+                        Some(link) => match open::that(link) {
+                            Ok(()) => format!("Opened {}", link),
+                            Err(e) => format!("Cannot open link: {}", e),
+                        },
+                        None => "No such link".to_string(),
+                    });
+                    // clear command input
+                    self.state.review_flags.show_links = false;
+                    self.filters.pop();
+                    self.command_input.clear();
                 }
             }
         } else if self.need_update {
-            self.emails = serde_json::from_str::<Response>(&get_email_list())
-                .unwrap()
-                .response;
+            if let Some(emails) = self.cache.load_envelopes() {
+                self.emails = emails;
+            }
             self.state.msg_table = TableState::default();
+            self.sync();
             self.need_update = false;
         }
     }
 
+    /// Copy a field of the message being read, or of the selected envelope.
+    fn yank(&mut self, field: YankField) {
+        let envelope = self
+            .state
+            .msg_table
+            .selected()
+            .and_then(|i| self.emails.get(i));
+        // parse the cached message if the envelope lacks the field
+        let cached = match (&self.state.message, envelope) {
+            (None, Some(envelope)) if field == YankField::MessageId => self
+                .cache
+                .load_message(envelope)
+                .and_then(|raw| Message::parse(raw.as_bytes()).ok()),
+            _ => None,
+        };
+        let message = self.state.message.as_ref().or(cached.as_ref());
+
+        let header = match field {
+            YankField::Sender => "From",
+            YankField::Subject => "Subject",
+            YankField::Date => "Date",
+            YankField::MessageId => "Message-ID",
+        };
+        let text = match (message, envelope) {
+            (Some(message), _) => message.header(header).map(|value| value.to_string()),
+            (None, Some(envelope)) => match field {
+                YankField::Sender => Some(envelope.sender.clone()),
+                YankField::Subject => Some(envelope.subject.clone()),
+                YankField::Date => Some(envelope.date.clone()),
+                YankField::MessageId => None,
+            },
+            (None, None) => None,
+        };
+
+        self.info = Some(match text {
+            Some(text) => match clipboard::copy(&text, &self.config.clipboard_command) {
+                Ok(()) => format!("Copied {}", text),
+                Err(e) => format!("Cannot copy: {}", e),
+            },
+            None if field == YankField::MessageId && envelope.is_some() => {
+                "Message id unknown until the message is read".to_string()
+            }
+            None => format!("No {} to copy", header.to_lowercase()),
+        });
+    }
+
+    /// Show the envelopes of another folder.
+    fn switch_folder(&mut self, folder: &str) {
+        self.config.set_folder(folder);
+        self.mail = mail::open(&self.config, self.version);
+        self.marked.clear();
+        self.cache = Cache::new(&self.config);
+        self.emails = self.cache.load_envelopes().unwrap_or_default();
+        self.known = self.emails.clone();
+        self.state.msg_table = TableState::default();
+        self.new_mail = 0;
+        self.last_sync = None;
+        if self.watcher.is_some() {
+            self.watcher = None;
+            match Watcher::spawn(watch_command(&self.config, self.version)) {
+                Ok(watcher) => self.watcher = Some(watcher),
+                Err(e) => self.info = Some(format!("Cannot watch folder, polling: {}", e)),
+            }
+        }
+        // envelopes of the previous folder may still be coming
+        self.resync = false;
+        self.sync();
+    }
+
+    /// Candidates for the word before the cursor of the command line.
+    fn completions(&mut self) -> Vec<String> {
+        let words = self.command_input.words_before_cursor();
+        if words.len() <= 1 {
+            return COMMANDS.iter().map(|(name, _)| name.to_string()).collect();
+        }
+
+        let previous = words[words.len() - 2].to_lowercase();
+        match words[0].to_lowercase().as_str() {
+            "export" if words.len() == 2 => export::FORMATS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            "folder" | "move" => {
+                if self.folders.is_none() {
+                    match self.mail.folders() {
+                        Ok(folders) => self.folders = Some(folders),
+                        Err(e) => self.info = Some(format!("Cannot list folders: {}", e)),
+                    }
+                }
+                self.folders.clone().unwrap_or_default()
+            }
+            "search" if self.config.backend == BackendKind::Notmuch => {
+                QUERY_WORDS.iter().map(|w| w.to_string()).collect()
+            }
+            "search" if previous == "from" || previous == "to" => {
+                let mut senders: Vec<String> = self
+                    .emails
+                    .iter()
+                    .map(|msg| search_address(&msg.sender))
+                    .collect();
+                senders.sort();
+                senders.dedup();
+                senders
+            }
+            "search" => SEARCH_KEYWORDS.iter().map(|k| k.to_string()).collect(),
+            "tag" => {
+                let mut flags: Vec<Flag> = vec![
+                    Flag::Seen,
+                    Flag::Answered,
+                    Flag::Flagged,
+                    Flag::Deleted,
+                    Flag::Draft,
+                ];
+                for flag in self.emails.iter().flat_map(|msg| &msg.flags) {
+                    if !flags.contains(flag) {
+                        flags.push(flag.clone());
+                    }
+                }
+                ["+", "-"]
+                    .iter()
+                    .flat_map(|sign| {
+                        flags
+                            .iter()
+                            .map(move |flag| format!("{}{}", sign, mail::flag_name(flag)))
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Fetch envelopes in background, the list is updated once they arrive.
+    pub fn sync(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let mail = self.mail.clone();
+        thread::spawn(move || {
+            let result = mail.envelopes().map_err(|e| e.to_string());
+            // the app may have quit in the meantime
+            let _ = tx.send(result);
+        });
+        self.sync = Some(rx);
+        self.last_poll = Instant::now();
+    }
+
+    pub fn syncing(&self) -> bool {
+        self.sync.is_some()
+    }
+
+    pub fn watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Refresh the list if the watch process reported changes.
+    fn poll_watcher(&mut self) {
+        let mut changed = false;
+        while let Some(event) = self.watcher.as_mut().and_then(Watcher::try_next) {
+            match event {
+                WatchEvent::Changed => changed = true,
+                WatchEvent::Exited(output) => {
+                    // unsupported or failing, fall back to polling
+                    self.watcher = None;
+                    self.info = Some(format!("Watch stopped, polling: {}", output));
+                }
+            }
+        }
+
+        if changed {
+            if self.syncing() {
+                self.resync = true;
+            } else {
+                self.sync();
+            }
+        }
+    }
+
+    /// Check whether envelopes fetched in background have arrived.
+    fn poll_sync(&mut self) {
+        let result = match &self.sync {
+            Some(rx) => match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err("sync interrupted".to_string()),
+            },
+            None => return,
+        };
+        self.sync = None;
+
+        match result {
+            Ok(emails) => {
+                self.offline = false;
+                self.last_sync = Some(Instant::now());
+                // count unseen envelopes which were not known yet, unless nothing was known,
+                // and never in files which are opened to be read on purpose
+                if !self.known.is_empty() && !self.config.backend.reads_file() {
+                    let new: Vec<&Msg> = emails
+                        .iter()
+                        .filter(|msg| !msg.flags.contains(&Flag::Seen))
+                        .filter(|msg| !self.known.iter().any(|m| m.same(msg)))
+                        .collect();
+                    self.new_mail += new.len();
+                    if let Err(e) = self.config.notify.notify(self.config.folder(), &new) {
+                        self.info = Some(format!("Cannot notify: {}", e));
+                    }
+                }
+                if let Err(e) = self.cache.save_envelopes(&emails) {
+                    self.info = Some(format!("Cannot write cache: {}", e));
+                }
+                self.known = emails.clone();
+                // filtered lists are refreshed when the filter is cancelled
+                if self.filters.is_empty() {
+                    // keep the same envelope selected even if its position changed
+                    let index = self.state.msg_table.selected().map(|i| {
+                        self.emails
+                            .get(i)
+                            .and_then(|selected| emails.iter().position(|m| m.same(selected)))
+                            .unwrap_or_else(|| i.min(emails.len().saturating_sub(1)))
+                    });
+                    self.state
+                        .msg_table
+                        .select(index.filter(|_| !emails.is_empty()));
+                    self.marked.retain(|m| emails.iter().any(|msg| msg.same(m)));
+                    self.emails = emails;
+                }
+            }
+            Err(_) => self.offline = true,
+        }
+
+        if self.resync {
+            self.resync = false;
+            self.sync();
+        }
+    }
+
+    /// Get the raw message from the backend, or from the cache when offline.
+    fn read_raw(&mut self, id: usize) -> io::Result<String> {
+        let envelope = self.emails.iter().find(|m| m.id == id).cloned();
+
+        if !self.offline {
+            match self.mail.read(id) {
+                Ok(raw) => {
+                    self.mark_seen(id);
+                    if let Some(envelope) = &envelope {
+                        // cache failures only cost a refetch later
+                        let _ = self.cache.save_message(envelope, &raw);
+                    }
+                    return Ok(raw);
+                }
+                // other errors are about the message, like a wrong id
+                Err(e) if e.kind() == io::ErrorKind::NotConnected => self.offline = true,
+                Err(e) => return Err(e),
+            }
+        }
+        envelope
+            .and_then(|envelope| self.cache.load_message(&envelope))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not cached while offline"))
+    }
+
+    /// Mark the message read as seen, which himalaya does by itself when reading.
+    fn mark_seen(&mut self, id: usize) {
+        if !matches!(
+            self.config.backend,
+            BackendKind::Maildir | BackendKind::Notmuch
+        ) {
+            return;
+        }
+        let msg = match self.emails.iter_mut().find(|m| m.id == id) {
+            Some(msg) if !msg.flags.contains(&Flag::Seen) => msg,
+            _ => return,
+        };
+        match self.mail.add_flag(id, &Flag::Seen) {
+            Ok(()) => msg.flags.push(Flag::Seen),
+            Err(e) => self.info = Some(format!("Cannot mark message as seen: {}", e)),
+        }
+    }
+
+    /// Add the flag to the selected message, or remove it if already set.
+    fn toggle_flag(&mut self, flag: Flag) {
+        let msg = match self
+            .state
+            .msg_table
+            .selected()
+            .and_then(|i| self.emails.get_mut(i))
+        {
+            Some(msg) => msg,
+            None => return,
+        };
+        let set = msg.flags.contains(&flag);
+        let result = if set {
+            self.mail.remove_flag(msg.id, &flag)
+        } else {
+            self.mail.add_flag(msg.id, &flag)
+        };
+        match result {
+            Ok(()) => {
+                // show the change until the next sync confirms it
+                if set {
+                    msg.flags.retain(|f| *f != flag);
+                } else {
+                    msg.flags.push(flag);
+                }
+                self.sync();
+            }
+            Err(e) => self.info = Some(format!("Cannot change flags: {}", e)),
+        }
+    }
+
+    /// Add tags given as `+tag` or `tag` to the selected message, and remove `-tag` ones.
+    fn tag_selected(&mut self, changes: &[String]) {
+        let msg = match self
+            .state
+            .msg_table
+            .selected()
+            .and_then(|i| self.emails.get_mut(i))
+        {
+            Some(msg) => msg,
+            None => {
+                self.info = Some("No message selected".to_string());
+                return;
+            }
+        };
+        for change in changes.iter().filter(|change| !change.is_empty()) {
+            let (remove, tag) = match change.strip_prefix('-') {
+                Some(tag) => (true, tag),
+                None => (false, change.trim_start_matches('+')),
+            };
+            let flag = Flag::Custom(tag.to_string());
+            let result = if remove {
+                self.mail.remove_flag(msg.id, &flag)
+            } else {
+                self.mail.add_flag(msg.id, &flag)
+            };
+            if let Err(e) = result {
+                self.info = Some(format!("Cannot tag message: {}", e));
+                return;
+            }
+            msg.flags.retain(|f| *f != flag);
+            if !remove {
+                msg.flags.push(flag);
+            }
+        }
+        self.sync();
+    }
+
+    /// Envelope of the message being read, or of the selected one in the list.
+    fn current_envelope(&self) -> Option<Msg> {
+        let id = match (&self.state.message, self.curr_filter()) {
+            (Some(_), Some(Filter(filter))) => filter
+                .split(' ')
+                .nth(1)
+                .and_then(|id| id.parse::<usize>().ok()),
+            _ => self
+                .state
+                .msg_table
+                .selected()
+                .and_then(|i| self.emails.get(i))
+                .map(|msg| msg.id),
+        }?;
+        self.emails.iter().find(|msg| msg.id == id).cloned()
+    }
+
+    /// Save the current message as an `.eml` file into the downloads directory.
+    fn export_message(&mut self) {
+        let msg = match self.current_envelope() {
+            Some(msg) => msg,
+            None => {
+                self.info = Some("No message selected".to_string());
+                return;
+            }
+        };
+        self.info = Some(match self.read_raw(msg.id) {
+            Ok(raw) => match export::save_eml(
+                &self.config.downloads_dir,
+                &self.config.export_template,
+                &msg,
+                &raw,
+            ) {
+                Ok(path) => format!("Saved {}", path.display()),
+                Err(e) => format!("Cannot export message: {}", e),
+            },
+            Err(e) => format!("Cannot read message: {}", e),
+        });
+    }
+
+    /// Export the marked messages, or the current one if none is marked.
+    fn export_messages(&mut self, format: export::Format, path: &str) {
+        let envelopes: Vec<Msg> = if self.marked.is_empty() {
+            self.current_envelope().into_iter().collect()
+        } else {
+            self.emails
+                .iter()
+                .filter(|msg| self.marked.iter().any(|m| m.same(msg)))
+                .cloned()
+                .collect()
+        };
+        if envelopes.is_empty() {
+            self.info = Some("No message selected".to_string());
+            return;
+        }
+
+        let mut messages = Vec::new();
+        for msg in envelopes {
+            match self.read_raw(msg.id) {
+                Ok(raw) => messages.push((msg, raw)),
+                Err(e) => {
+                    self.info = Some(format!("Cannot read message {}: {}", msg.id, e));
+                    return;
+                }
+            }
+        }
+        self.info = Some(
+            match export::export(format, path, &self.config.export_template, &messages) {
+                Ok(paths) => {
+                    self.marked.clear();
+                    match &paths[..] {
+                        [path] => format!("Exported to {}", path.display()),
+                        _ => format!("Exported {} files", paths.len()),
+                    }
+                }
+                Err(e) => format!("Cannot export messages: {}", e),
+            },
+        );
+    }
+
+    /// Pipe the current message to a shell command, showing its output or giving it the
+    /// terminal.
+    fn pipe_message(&mut self, pipe: Pipe) {
+        let raw = match self.current_envelope().map(|msg| self.read_raw(msg.id)) {
+            Some(Ok(raw)) => raw,
+            Some(Err(e)) => {
+                self.info = Some(format!("Cannot read message: {}", e));
+                return;
+            }
+            None => {
+                self.info = Some("No message to pipe".to_string());
+                return;
+            }
+        };
+        let input = if pipe.decoded {
+            match Message::parse(raw.as_bytes()) {
+                Ok(message) => export::render(&message, false),
+                Err(e) => {
+                    self.info = Some(format!("Cannot decode message: {}", e));
+                    return;
+                }
+            }
+        } else {
+            raw
+        };
+
+        if pipe.interactive {
+            match self
+                .temp_dir()
+                .and_then(|dir| pipe.interactive(&input, &dir))
+            {
+                Ok(command) => self.interactive = Some(command),
+                Err(e) => self.info = Some(format!("Cannot run command: {}", e)),
+            }
+            return;
+        }
+        match pipe.run(&input) {
+            Ok((success, text)) => {
+                self.pipe_output = Some(PipeOutput {
+                    command: pipe.command,
+                    success,
+                    text,
+                    scroll: 0,
+                });
+                self.keymap.switch_to(KeyMode::Output);
+            }
+            Err(e) => self.info = Some(format!("Cannot run command: {}", e)),
+        }
+    }
+
+    /// Move the selected message to another folder.
+    fn move_selected(&mut self, folder: &str) {
+        let id = match self
+            .state
+            .msg_table
+            .selected()
+            .and_then(|i| self.emails.get(i))
+        {
+            Some(msg) => msg.id,
+            None => {
+                self.info = Some("No message selected".to_string());
+                return;
+            }
+        };
+        match self.mail.move_to(id, folder) {
+            Ok(()) => {
+                self.info = Some(format!("Moved to {}", folder));
+                self.sync();
+            }
+            Err(e) => self.info = Some(format!("Cannot move message: {}", e)),
+        }
+    }
+
     pub fn curr_filter(&self) -> Option<&Filter> {
         self.filters.last()
     }
+
+    /// Called when the interactive program exits and the terminal is restored.
+    pub fn on_resume(&mut self, status: io::Result<ExitStatus>) {
+        if let Err(e) = status {
+            self.info = Some(format!("Cannot run program: {}", e));
+        }
+
+        if let Some(draft) = &mut self.draft {
+            if let Err(e) = draft.reload() {
+                self.info = Some(format!("Cannot read draft: {}", e));
+            }
+            if self.keymap.mode != KeyMode::Compose {
+                self.keymap.switch_to(KeyMode::Compose);
+            }
+        }
+    }
+
+    /// Drop the draft, going back to the message being read if any.
+    fn close_draft(&mut self) {
+        self.draft = None;
+        if self.state.message.is_some() {
+            self.keymap.switch_to(KeyMode::Review);
+        } else {
+            self.keymap.switch_to(KeyMode::Motion);
+        }
+    }
+
+    /// Reply to the message being read, quoting the selected lines only.
+    fn quote_reply(&mut self) {
+        let message = match &self.state.message {
+            Some(message) => message,
+            None => return,
+        };
+        // ids of other backends mean nothing to himalaya
+        let id = match self.curr_filter() {
+            Some(Filter(filter)) if self.config.backend == BackendKind::Himalaya => filter
+                .split(' ')
+                .nth(1)
+                .and_then(|id| id.parse::<usize>().ok()),
+            _ => None,
+        };
+        let template = id
+            .and_then(|id| get_reply_template(&self.config, self.version, id))
+            .unwrap_or_else(|| {
+                let subject = message.header("Subject").unwrap_or_default();
+                let mut template = format!(
+                    "To: {}\nSubject: {}{}\n",
+                    message
+                        .header("Reply-To")
+                        .or_else(|| message.header("From"))
+                        .unwrap_or_default(),
+                    if subject.to_lowercase().starts_with("re:") {
+                        ""
+                    } else {
+                        "Re: "
+                    },
+                    subject
+                );
+                if let Some(message_id) = message.header("Message-ID") {
+                    template.push_str(&format!("In-Reply-To: {}\n", message_id));
+                }
+                template
+            });
+        // keep the headers of the template, and quote the selection as body
+        let headers = template
+            .replace("\r\n", "\n")
+            .split("\n\n")
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let quoted: Vec<String> = self
+            .state
+            .selection()
+            .iter()
+            .map(|line| format!("> {}", line).trim_end().to_string())
+            .collect();
+
+        self.open_draft(&format!("{}\n\n{}\n", headers, quoted.join("\n")));
+    }
+
+    /// Create a draft from the template and edit it.
+    fn open_draft(&mut self, template: &str) {
+        match self.temp_dir().and_then(|dir| Draft::new(template, &dir)) {
+            Ok(draft) => {
+                self.interactive = Some(editor_command(&draft.path));
+                self.draft = Some(draft);
+            }
+            Err(e) => self.info = Some(format!("Cannot create draft: {}", e)),
+        }
+    }
+
+    fn send_draft(&mut self) {
+        if let Some(draft) = &self.draft {
+            match draft
+                .to_mime()
+                .and_then(|raw| send_message(&self.config, self.version, &raw))
+            {
+                Ok(()) => {
+                    self.info = Some("Message sent".to_string());
+                    self.close_draft();
+                }
+                Err(e) => self.info = Some(format!("Cannot send message: {}", e)),
+            }
+        }
+    }
+
+    /// Save the selected attachment, or all of them, into the downloads directory.
+    fn save_attachments(&mut self, all: bool) {
+        let message = match &self.state.message {
+            Some(message) if !message.attachments.is_empty() => message,
+            _ => return,
+        };
+        let attachments = if all {
+            &message.attachments[..]
+        } else {
+            let i = self.state.review_flags.attachment;
+            &message.attachments[i..=i]
+        };
+
+        let mut saved = Vec::new();
+        for attachment in attachments {
+            match attachment.save(&self.config.downloads_dir) {
+                Ok(path) => saved.push(path.display().to_string()),
+                Err(e) => {
+                    self.info = Some(format!("Cannot save {}: {}", attachment.name(), e));
+                    return;
+                }
+            }
+        }
+        self.info = Some(format!("Saved {}", saved.join(", ")));
+    }
+
+    /// Open the selected attachment with the system handler.
+    fn open_attachment(&mut self) {
+        let dir = self.temp_dir();
+        let attachment = match &self.state.message {
+            Some(message) => match message.attachments.get(self.state.review_flags.attachment) {
+                Some(attachment) => attachment,
+                None => return,
+            },
+            None => return,
+        };
+
+        // opening the attachment again reuses its file
+        let result = dir.and_then(|dir| {
+            let path = dir.join(attachment.name());
+            fs::write(&path, &attachment.content)?;
+            open::that(&path).map(|_| path)
+        });
+        self.info = Some(match result {
+            Ok(path) => format!("Opened {}", path.display()),
+            Err(e) => format!("Cannot open {}: {}", attachment.name(), e),
+        });
+    }
+
+    /// Private directory of the files given to other programs, created on first use.
+    fn temp_dir(&mut self) -> io::Result<PathBuf> {
+        if let Some(dir) = &self.temp_dir {
+            return Ok(dir.clone());
+        }
+        let dir = private_temp_dir("himalaya-tui")?;
+        self.temp_dir = Some(dir.clone());
+        Ok(dir)
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.temp_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Address of the sender to search for, or the whole sender quoted if it has none.
+fn search_address(sender: &str) -> String {
+    match sender.rsplit_once('<') {
+        Some((_, addr)) if addr.contains('@') => addr.trim_end_matches('>').to_string(),
+        _ if sender.contains(char::is_whitespace) => format!("\"{}\"", sender),
+        _ => sender.to_string(),
+    }
+}
+
+/// Command opening the file in the editor of the user.
+fn editor_command(path: &Path) -> Command {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut args = editor.split_whitespace();
+    let mut command = Command::new(args.next().unwrap_or("vi"));
+    command.args(args).arg(path);
+    command
 }
 
 #[derive(Debug)]
 pub struct AppState {
     pub msg_table: TableState,
     pub content: (String, u16),
+    pub message: Option<Message>,
     pub review_flags: ReviewFlags,
 }
 
 impl AppState {
+    /// Show the body part of the current message selected by the review flags.
+    pub fn show_body(&mut self) {
+        if let Some(message) = &self.message {
+            let body = match message.body(&self.review_flags.part) {
+                Some(part) => part.body.clone(),
+                None => String::new(),
+            };
+            self.content = (body, 0);
+            self.review_flags.html = None;
+        }
+    }
+
+    /// Lines of the body selected in visual mode.
+    pub fn selection(&self) -> &[String] {
+        let flags = &self.review_flags;
+        match flags.visual {
+            Some((anchor, cursor)) => {
+                let end = anchor.max(cursor).min(flags.lines.len().saturating_sub(1));
+                flags
+                    .lines
+                    .get(anchor.min(cursor)..=end)
+                    .unwrap_or_default()
+            }
+            None => &[],
+        }
+    }
+
     pub fn next(&mut self, size: usize) {
         if size != 0 {
             let i = match self.msg_table.selected() {
@@ -215,23 +1500,122 @@ pub mod data {
     use core::fmt;
     use std::fmt::Display;
 
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
+
+    use tui::text::Spans;
 
+    use crate::message::PartKind;
+
+    /// Envelopes listed by himalaya, wrapped in `response` before 0.8.
     #[derive(Debug, Deserialize)]
-    pub struct Response {
-        pub response: Vec<Msg>,
+    #[serde(untagged)]
+    pub enum Response {
+        Wrapped { response: Vec<Msg> },
+        Bare(Vec<Msg>),
+    }
+
+    /// Parse the envelopes listed by any version of himalaya.
+    pub fn parse_envelopes(output: &str) -> serde_json::Result<Vec<Msg>> {
+        Ok(match serde_json::from_str(output)? {
+            Response::Wrapped { response } | Response::Bare(response) => response,
+        })
     }
 
-    #[derive(Debug, Deserialize, Clone)]
+    #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+    #[serde(try_from = "RawMsg")]
     pub struct Msg {
         pub id: usize,
         pub flags: Vec<Flag>,
         pub subject: String,
         pub sender: String,
         pub date: String,
+        pub has_attachment: bool,
+    }
+
+    /// Envelope as written by the different versions of himalaya.
+    #[derive(Deserialize)]
+    struct RawMsg {
+        /// a number, or a string since 0.6
+        id: Id,
+        #[serde(default)]
+        flags: Vec<Flag>,
+        #[serde(default)]
+        subject: Option<String>,
+        /// `sender` as a string before 0.6, then `from` as an address
+        #[serde(default, alias = "from")]
+        sender: Option<Address>,
+        #[serde(default)]
+        date: Option<String>,
+        /// only listed since 1.0
+        #[serde(default)]
+        has_attachment: bool,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(usize),
+        Text(String),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Address {
+        Text(String),
+        Mailbox {
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default, alias = "address", alias = "email")]
+            addr: Option<String>,
+        },
+        List(Vec<Address>),
+    }
+
+    impl Address {
+        /// Name of the sender, or its address if it has none.
+        fn display(self) -> String {
+            match self {
+                Address::Text(text) => text,
+                Address::Mailbox { name, addr } => name
+                    .filter(|name| !name.is_empty())
+                    .or(addr)
+                    .unwrap_or_default(),
+                Address::List(list) => list
+                    .into_iter()
+                    .map(Address::display)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }
+        }
+    }
+
+    impl TryFrom<RawMsg> for Msg {
+        type Error = String;
+
+        fn try_from(raw: RawMsg) -> Result<Self, Self::Error> {
+            let id = match raw.id {
+                Id::Number(id) => id,
+                Id::Text(id) => id
+                    .parse()
+                    .map_err(|_| format!("invalid envelope id {:?}", id))?,
+            };
+            Ok(Msg {
+                id,
+                flags: raw.flags,
+                subject: raw.subject.unwrap_or_default(),
+                sender: raw.sender.map(Address::display).unwrap_or_default(),
+                date: raw.date.unwrap_or_default(),
+                has_attachment: raw.has_attachment,
+            })
+        }
     }
 
     impl Msg {
+        /// Check whether both envelopes are the same message, ids alone may be reused.
+        pub fn same(&self, other: &Msg) -> bool {
+            self.id == other.id && self.subject == other.subject && self.date == other.date
+        }
+
         pub fn flags_string(&self) -> String {
             let mut flags = String::new();
             flags.push_str(if self.flags.contains(&Flag::Seen) {
@@ -253,7 +1637,8 @@ pub mod data {
         }
     }
 
-    #[derive(Debug, Deserialize, PartialEq, Clone)]
+    #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+    #[serde(from = "RawFlag")]
     pub enum Flag {
         Seen,
         Answered,
@@ -265,6 +1650,37 @@ pub mod data {
         Custom(String),
     }
 
+    /// Flag as written by the different versions of himalaya, like `"Seen"`, `"seen"`,
+    /// `"\\Seen"` or `{"Custom": "work"}`.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawFlag {
+        Name(String),
+        Custom {
+            #[serde(rename = "Custom", alias = "custom")]
+            custom: String,
+        },
+    }
+
+    impl From<RawFlag> for Flag {
+        fn from(raw: RawFlag) -> Self {
+            let name = match raw {
+                RawFlag::Name(name) => name,
+                RawFlag::Custom { custom } => return Flag::Custom(custom),
+            };
+            match name.trim_start_matches('\\').to_lowercase().as_str() {
+                "seen" => Flag::Seen,
+                "answered" => Flag::Answered,
+                "flagged" => Flag::Flagged,
+                "deleted" => Flag::Deleted,
+                "draft" => Flag::Draft,
+                "recent" => Flag::Recent,
+                "maycreate" => Flag::MayCreate,
+                _ => Flag::Custom(name),
+            }
+        }
+    }
+
     impl Display for Flag {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             let flag = match self {
@@ -279,10 +1695,40 @@ pub mod data {
         }
     }
 
+    /// HTML body rendered once for the message, as it is drawn on every frame.
+    #[derive(Debug)]
+    pub struct RenderedHtml {
+        /// whether links are labeled with their index
+        pub labeled: bool,
+        pub lines: Vec<Spans<'static>>,
+        pub links: Vec<String>,
+    }
+
     #[derive(Debug, Default)]
     pub struct ReviewFlags {
         pub show_stats: bool,
         pub show_links: bool,
+        pub full_headers: bool,
+        pub raw_source: bool,
+        /// fold long quoted blocks and the signature
+        pub fold: bool,
+        /// index of the selected attachment
+        pub attachment: usize,
+        pub part: PartKind,
         pub links: Vec<String>,
+        /// rendering of the HTML part, dropped when the body changes
+        pub html: Option<RenderedHtml>,
+        /// text searched in the body
+        pub search: Option<String>,
+        /// body lines matching the search, and the index of the current one
+        pub matches: Vec<usize>,
+        pub current_match: usize,
+        /// lines selected in visual mode, as the anchor and cursor
+        pub visual: Option<(usize, usize)>,
+        /// whether to scroll to the current match or the visual cursor
+        pub jump: bool,
+        /// text of the body lines, and the row each one starts at once wrapped
+        pub lines: Vec<String>,
+        pub line_rows: Vec<usize>,
     }
 }
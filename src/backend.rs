@@ -13,16 +13,16 @@ use tui::{
     Terminal,
 };
 
-use crate::{app::App, ui};
+use crate::{app::App, config::Config, ui};
 
-pub fn run(tick_rate: Duration) -> Result<(), Box<dyn Error>> {
+pub fn run(tick_rate: Duration, config: Config) -> Result<(), Box<dyn Error>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new();
+    let app = App::new(config);
     let _res = run_app(&mut terminal, app, tick_rate);
 
     // restore terminal
@@ -37,7 +37,7 @@ pub fn run(tick_rate: Duration) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn run_app<B: Backend>(
+fn run_app<B: Backend + io::Write>(
     terminal: &mut Terminal<B>,
     mut app: App,
     tick_rate: Duration,
@@ -59,6 +59,16 @@ fn run_app<B: Backend>(
                 // Input mode
             }
         }
+        if let Some(mut command) = app.interactive.take() {
+            // give the terminal to the program until it exits
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+            let status = command.status();
+            enable_raw_mode()?;
+            execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+            terminal.clear()?;
+            app.on_resume(status);
+        }
         if last_tick.elapsed() >= tick_rate {
             app.on_tick();
             last_tick = Instant::now();
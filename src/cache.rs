@@ -0,0 +1,104 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{app::data::Msg, config::Config, mail::BackendKind};
+
+/// On-disk cache of envelopes and read messages, one directory per backend, account and
+/// folder, only readable by the user.
+///
+/// Files opened directly are not cached: they are always at hand, and files of the same
+/// name would share their cache.
+#[derive(Debug)]
+pub struct Cache {
+    dir: Option<PathBuf>,
+}
+
+impl Cache {
+    pub fn new(config: &Config) -> Self {
+        // the maildir or notmuch index stands for the account of himalaya
+        let account = match config.backend {
+            BackendKind::Maildir => config
+                .maildir
+                .clone()
+                .unwrap_or_default()
+                .display()
+                .to_string(),
+            BackendKind::Notmuch => config.notmuch.display().to_string(),
+            _ => config.account().to_string(),
+        };
+        let dir = (!config.backend.reads_file()).then(|| {
+            config
+                .cache_dir
+                .join(config.backend.name())
+                .join(sanitize(&account))
+                .join(sanitize(config.folder()))
+        });
+        Cache { dir }
+    }
+
+    pub fn load_envelopes(&self) -> Option<Vec<Msg>> {
+        let content = fs::read_to_string(self.dir.as_ref()?.join("envelopes.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save_envelopes(&self, emails: &[Msg]) -> io::Result<()> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        create_dir(dir)?;
+        fs::write(dir.join("envelopes.json"), serde_json::to_string(emails)?)
+    }
+
+    /// Load the raw message of the envelope if it has been read before.
+    pub fn load_message(&self, msg: &Msg) -> Option<String> {
+        fs::read_to_string(self.message_path(msg)?).ok()
+    }
+
+    pub fn save_message(&self, msg: &Msg, raw: &str) -> io::Result<()> {
+        let path = match self.message_path(msg) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            create_dir(dir)?;
+        }
+        fs::write(path, raw)
+    }
+
+    /// Path of a cached message.
+    ///
+    /// Ids are not stable across sessions, so the name also depends on the envelope content.
+    fn message_path(&self, msg: &Msg) -> Option<PathBuf> {
+        // FNV-1a, stable across builds unlike the hasher of the standard library
+        let hash = [&msg.subject, &msg.sender, &msg.date]
+            .iter()
+            .flat_map(|field| field.bytes().chain([0]))
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        let dir = self.dir.as_ref()?.join("messages");
+        Some(dir.join(format!("{}-{:016x}.eml", msg.id, hash)))
+    }
+}
+
+/// Create the directory and its missing parents, only readable by the user.
+fn create_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Make a name safe to use as a directory name.
+fn sanitize(name: &str) -> String {
+    if name == "." || name == ".." {
+        return "_".to_string();
+    }
+    name.chars()
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .collect()
+}
@@ -0,0 +1,26 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Copy text to the clipboard of the terminal with an OSC 52 sequence, which also works
+/// over SSH, and to the system clipboard if a command like `["wl-copy"]` is given.
+pub fn copy(text: &str, command: &[String]) -> io::Result<()> {
+    if let Some((program, args)) = command.split_first() {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        // clipboard tools may keep running to serve the selection
+        std::thread::spawn(move || child.wait());
+    }
+
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
@@ -0,0 +1,376 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::utils::{civil_date, fuzzy_score, human_size, unique_path};
+
+/// Message being composed, edited as a plain text file with headers followed by the body.
+#[derive(Debug)]
+pub struct Draft {
+    pub path: PathBuf,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub attachments: Vec<PathBuf>,
+    /// index of the selected attachment
+    pub selected: usize,
+}
+
+impl Draft {
+    /// Create a draft file from the given template in the private directory, only
+    /// readable by the user.
+    pub fn new(template: &str, dir: &Path) -> io::Result<Self> {
+        let path = unique_path(dir, "draft.eml");
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)?.write_all(template.as_bytes())?;
+
+        let mut draft = Draft {
+            path,
+            headers: Vec::new(),
+            body: String::new(),
+            attachments: Vec::new(),
+            selected: 0,
+        };
+        draft.reload()?;
+        Ok(draft)
+    }
+
+    /// Read headers and body back from the draft file after edition.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let content = fs::read_to_string(&self.path)?;
+        let (headers, offset) = mailparse::parse_headers(content.as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // the draft is written in UTF-8, unlike raw headers of received messages
+        self.headers = headers
+            .iter()
+            .map(|h| {
+                let value = String::from_utf8_lossy(h.get_value_raw());
+                let value = value.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+                (h.get_key(), value)
+            })
+            .collect();
+        self.body = content[offset..].to_string();
+        Ok(())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Size of an attached file for display.
+    pub fn attachment_size(path: &Path) -> String {
+        fs::metadata(path)
+            .map(|m| human_size(m.len() as usize))
+            .unwrap_or_else(|_| "missing".to_string())
+    }
+
+    pub fn remove_selected(&mut self) {
+        if self.selected < self.attachments.len() {
+            self.attachments.remove(self.selected);
+            self.selected = self.selected.min(self.attachments.len().saturating_sub(1));
+        }
+    }
+
+    /// Build the MIME message to send, with a multipart body iff there are attachments.
+    pub fn to_mime(&self) -> io::Result<Vec<u8>> {
+        let mut message = Vec::new();
+        for (key, value) in &self.headers {
+            // headers describing the body are generated below
+            if key.to_lowercase().starts_with("content-")
+                || key.eq_ignore_ascii_case("mime-version")
+            {
+                continue;
+            }
+            push_line(
+                &mut message,
+                &format!("{}: {}", key, encode_header(key, value)),
+            );
+        }
+        if self.header("Date").is_none() {
+            push_line(
+                &mut message,
+                &format!("Date: {}", rfc2822_date(SystemTime::now())),
+            );
+        }
+        push_line(&mut message, "MIME-Version: 1.0");
+
+        let text_headers = [
+            "Content-Type: text/plain; charset=utf-8",
+            "Content-Transfer-Encoding: 8bit",
+        ];
+        let body = self.body.lines().collect::<Vec<_>>().join("\r\n");
+
+        if self.attachments.is_empty() {
+            for header in text_headers {
+                push_line(&mut message, header);
+            }
+            push_line(&mut message, "");
+            push_line(&mut message, &body);
+            return Ok(message);
+        }
+
+        let boundary = format!(
+            "himalaya-tui-{}-{}",
+            process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default()
+        );
+        push_line(
+            &mut message,
+            &format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary),
+        );
+        push_line(&mut message, "");
+
+        push_line(&mut message, &format!("--{}", boundary));
+        for header in text_headers {
+            push_line(&mut message, header);
+        }
+        push_line(&mut message, "");
+        push_line(&mut message, &body);
+
+        for path in &self.attachments {
+            let content = fs::read(path)?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "attachment".to_string());
+            let mime = mime_guess::from_path(path).first_or_octet_stream();
+
+            push_line(&mut message, &format!("--{}", boundary));
+            push_line(
+                &mut message,
+                &format!("Content-Type: {}; {}", mime, encode_param("name", &name)),
+            );
+            push_line(
+                &mut message,
+                &format!(
+                    "Content-Disposition: attachment; {}",
+                    encode_param("filename", &name)
+                ),
+            );
+            push_line(&mut message, "Content-Transfer-Encoding: base64");
+            push_line(&mut message, "");
+            let encoded = base64::encode(&content);
+            // base64 is ASCII only, so lines can be split at any byte
+            for chunk in encoded.as_bytes().chunks(76) {
+                message.extend_from_slice(chunk);
+                message.extend_from_slice(b"\r\n");
+            }
+        }
+        push_line(&mut message, &format!("--{}--", boundary));
+
+        Ok(message)
+    }
+}
+
+impl Drop for Draft {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn push_line(message: &mut Vec<u8>, line: &str) {
+    message.extend_from_slice(line.as_bytes());
+    message.extend_from_slice(b"\r\n");
+}
+
+/// Encode a non-ASCII header value with RFC 2047 encoded words.
+///
+/// Only display names of address headers are encoded, so that addresses stay readable.
+fn encode_header(key: &str, value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    let address_headers = ["from", "to", "cc", "bcc", "reply-to", "sender"];
+    if address_headers.contains(&key.to_lowercase().as_str()) {
+        value
+            .split(',')
+            .map(|address| match address.find('<') {
+                Some(i) => format!("{} {}", encode_words(address[..i].trim()), &address[i..]),
+                None => address.trim().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        encode_words(value)
+    }
+}
+
+/// Encode text as encoded words short enough to fit the limit of 75 chars each.
+fn encode_words(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > 45 {
+            words.push(format!("=?utf-8?B?{}?=", base64::encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?utf-8?B?{}?=", base64::encode(&chunk)));
+    }
+    // fold long headers, whitespace between encoded words is ignored when decoding
+    words.join("\r\n ")
+}
+
+/// Encode a MIME parameter, using RFC 2231 for non-ASCII values.
+fn encode_param(name: &str, value: &str) -> String {
+    if value.is_ascii() && !value.contains('"') {
+        format!("{}=\"{}\"", name, value)
+    } else {
+        let encoded: String = value
+            .bytes()
+            .map(|b| {
+                if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                    (b as char).to_string()
+                } else {
+                    format!("%{:02X}", b)
+                }
+            })
+            .collect();
+        format!("{}*=utf-8''{}", name, encoded)
+    }
+}
+
+/// Format a time as an RFC 2822 date in UTC.
+fn rfc2822_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default() as i64;
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    let (year, month, day) = civil_date(days);
+
+    let weekdays = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    let months = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        weekdays[days.rem_euclid(7) as usize],
+        day,
+        months[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Directory browser used to pick files to attach.
+#[derive(Debug)]
+pub struct FilePicker {
+    pub dir: PathBuf,
+    pub filter: String,
+    /// names of the entries in the directory, with whether they are directories
+    entries: Vec<(String, bool)>,
+    pub selected: usize,
+}
+
+impl FilePicker {
+    pub fn new(dir: PathBuf) -> Self {
+        let mut picker = FilePicker {
+            dir,
+            filter: String::new(),
+            entries: Vec::new(),
+            selected: 0,
+        };
+        picker.read_dir();
+        picker
+    }
+
+    fn read_dir(&mut self) {
+        self.entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| {
+                    let is_dir = e.path().is_dir();
+                    (e.file_name().to_string_lossy().to_string(), is_dir)
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        // directories first
+        self.entries
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.filter.clear();
+        self.selected = 0;
+    }
+
+    /// Entries matching the filter, best matches first.
+    pub fn matches(&self) -> Vec<&(String, bool)> {
+        let show_hidden = self.filter.starts_with('.');
+        let mut matches: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(name, _)| show_hidden || !name.starts_with('.'))
+            .filter_map(|entry| fuzzy_score(&self.filter, &entry.0).map(|score| (score, entry)))
+            .collect();
+        if !self.filter.is_empty() {
+            matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        matches.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn next(&mut self) {
+        let size = self.matches().len();
+        if size != 0 {
+            self.selected = (self.selected + 1) % size;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let size = self.matches().len();
+        if size != 0 {
+            self.selected = (self.selected + size - 1) % size;
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    /// Remove the last char of the filter, or go to the parent directory if there is none.
+    pub fn pop(&mut self) {
+        if self.filter.pop().is_none() {
+            if let Some(parent) = self.dir.parent() {
+                self.dir = parent.to_path_buf();
+                self.read_dir();
+            }
+        }
+        self.selected = 0;
+    }
+
+    /// Enter the selected directory, or return the selected file.
+    pub fn enter(&mut self) -> Option<PathBuf> {
+        let (name, is_dir) = self.matches().get(self.selected).cloned()?.clone();
+        let path = self.dir.join(name);
+        if is_dir {
+            self.dir = path;
+            self.read_dir();
+            None
+        } else {
+            Some(path)
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{mail::BackendKind, notify::NotifyConfig};
+
+/// User configuration, read from `$XDG_CONFIG_HOME/himalaya-tui/config.toml` by default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Directory where attachments are saved.
+    pub downloads_dir: PathBuf,
+    /// Path of the himalaya binary.
+    pub himalaya: PathBuf,
+    /// Global flags given to himalaya, like `["--account", "work"]`.
+    /// The account and the `--mailbox` are given after the subcommand since himalaya 1.0.
+    pub himalaya_args: Vec<String>,
+    /// Directory of the envelopes and messages cache.
+    pub cache_dir: PathBuf,
+    /// Seconds between background refreshes of the folder, 0 to disable.
+    pub poll_interval: u64,
+    /// Watch the folder with a long-running process instead of polling.
+    pub idle: bool,
+    /// Command watching the folder, printing a line on each change.
+    /// Defaults to `himalaya watch` with the global flags.
+    pub idle_command: Vec<String>,
+    /// Notifications of new messages.
+    pub notify: NotifyConfig,
+    /// File where the history of the command line is kept.
+    pub history_file: PathBuf,
+    /// Command receiving copied text on its stdin, like `["wl-copy"]` or `["xclip", "-sel", "c"]`,
+    /// in addition to the OSC 52 sequence understood by most terminals.
+    pub clipboard_command: Vec<String>,
+    /// Backend reading mail, `himalaya`, `maildir`, `notmuch`, `mbox` or `eml`.
+    pub backend: BackendKind,
+    /// Root of the Maildir account, read by the `maildir` backend.
+    pub maildir: Option<PathBuf>,
+    /// Path of the notmuch binary, run by the `notmuch` backend.
+    pub notmuch: PathBuf,
+    /// Mbox file read by the `mbox` backend.
+    pub mbox: Option<PathBuf>,
+    /// Message file read by the `eml` backend.
+    pub eml: Option<PathBuf>,
+    /// Name of exported files without extension, filled with `{id}`, `{subject}`,
+    /// `{sender}` and `{date}` of each message.
+    pub export_template: String,
+    /// Folder opened at start, `INBOX` by default.
+    /// Defaults to the `--mailbox` flag of `himalaya-args` if given.
+    pub folder: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            downloads_dir: dirs::download_dir()
+                .or_else(dirs::home_dir)
+                .unwrap_or_else(std::env::temp_dir),
+            himalaya: PathBuf::from("himalaya"),
+            himalaya_args: Vec::new(),
+            cache_dir: dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("himalaya-tui"),
+            poll_interval: 60,
+            idle: false,
+            idle_command: Vec::new(),
+            notify: NotifyConfig::default(),
+            history_file: dirs::data_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("himalaya-tui")
+                .join("history"),
+            clipboard_command: Vec::new(),
+            backend: BackendKind::default(),
+            maildir: None,
+            notmuch: PathBuf::from("notmuch"),
+            mbox: None,
+            eml: None,
+            export_template: "{date}-{subject}".to_string(),
+            folder: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from the given path, or from the default path if none is given.
+    ///
+    /// A missing file at the default path is not an error, default values are used instead.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read config {}: {}", path.display(), e))?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn poll_interval(&self) -> Option<Duration> {
+        match self.poll_interval {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Account given to himalaya, or `default` for its default account.
+    ///
+    /// Mbox and message files stand for their own account, named after the file.
+    pub fn account(&self) -> &str {
+        let file = match self.backend {
+            BackendKind::Mbox => &self.mbox,
+            BackendKind::Eml => &self.eml,
+            _ => &None,
+        };
+        match file {
+            Some(file) => file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("file"),
+            None => self.himalaya_account().unwrap_or("default"),
+        }
+    }
+
+    /// Account given to himalaya by `himalaya-args`, if any.
+    pub fn himalaya_account(&self) -> Option<&str> {
+        global_flag(&self.himalaya_args, "-a", "--account")
+    }
+
+    /// Folder chosen by the user, or by the `--mailbox` flag of `himalaya-args`.
+    pub fn chosen_folder(&self) -> Option<&str> {
+        self.folder
+            .as_deref()
+            .or_else(|| global_flag(&self.himalaya_args, "-m", "--mailbox"))
+            .or_else(|| global_flag(&self.himalaya_args, "-f", "--folder"))
+    }
+
+    /// Folder opened by the backend, or `INBOX` by default.
+    pub fn folder(&self) -> &str {
+        self.chosen_folder().unwrap_or("INBOX")
+    }
+
+    /// Make the backend use the given folder instead of the current one.
+    pub fn set_folder(&mut self, folder: &str) {
+        self.folder = Some(folder.to_string());
+    }
+
+    /// Flags of `himalaya-args` other than the account and the folder, which are placed
+    /// depending on the version of himalaya.
+    pub fn global_args(&self) -> Vec<&str> {
+        let mut args = Vec::new();
+        let mut iter = self.himalaya_args.iter();
+        while let Some(arg) = iter.next() {
+            let scope = ["-a", "--account", "-m", "--mailbox", "-f", "--folder"];
+            if scope.contains(&arg.as_str()) {
+                iter.next();
+            } else if !["--account=", "--mailbox=", "--folder="]
+                .iter()
+                .any(|flag| arg.starts_with(flag))
+            {
+                args.push(arg.as_str());
+            }
+        }
+        args
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("himalaya-tui").join("config.toml"))
+    }
+}
+
+/// Find the value of a global flag of himalaya, given either as `-f value`,
+/// `--flag value` or `--flag=value`.
+fn global_flag<'a>(args: &'a [String], short: &str, long: &str) -> Option<&'a str> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == short || arg == long {
+            return iter.next().map(|value| value.as_str());
+        }
+        if let Some(value) = arg
+            .strip_prefix(long)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -0,0 +1,278 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use mailparse::dateparse;
+
+use crate::{
+    app::data::Msg,
+    html,
+    message::{Message, PartKind},
+    utils::{civil_date, unique_path},
+};
+
+/// Formats of the `:export` command, with their description.
+pub const FORMATS: [(&str, &str); 4] = [
+    ("eml", "One raw message file per message"),
+    ("mbox", "Append all messages to an mbox file"),
+    ("text", "One plain text file per message"),
+    ("markdown", "One markdown file per message"),
+];
+
+/// Format messages are exported to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Eml,
+    Mbox,
+    Text,
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "eml" => Some(Format::Eml),
+            "mbox" => Some(Format::Mbox),
+            "text" | "txt" => Some(Format::Text),
+            "markdown" | "md" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Eml => "eml",
+            Format::Mbox => "mbox",
+            Format::Text => "txt",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+/// Fill the placeholders `{id}`, `{subject}`, `{sender}` and `{date}` of the template
+/// with the envelope, made safe for file names.
+pub fn render_template(template: &str, msg: &Msg) -> String {
+    template
+        .replace("{id}", &msg.id.to_string())
+        .replace("{subject}", &slug(&msg.subject))
+        .replace("{sender}", &slug(&msg.sender))
+        .replace(
+            "{date}",
+            &slug(msg.date.split(' ').next().unwrap_or_default()),
+        )
+}
+
+/// Lowercase words of the text joined by dashes, like `quarterly-report`.
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_matches('-').chars().take(60).collect();
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Name of the file a message is exported to, like `2022-03-01-quarterly-report.eml`.
+pub fn file_name(template: &str, msg: &Msg, extension: &str) -> String {
+    let name = render_template(template, msg);
+    let name = name.trim_matches(|c| c == '-' || c == '_' || c == '.');
+    match name {
+        "" => format!("{}.{}", msg.id, extension),
+        name => format!("{}.{}", name, extension),
+    }
+}
+
+/// Expand a leading `~` of a path typed by the user.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// File the message is written to: a new file named after the template if the path is a
+/// directory, or the path itself with its placeholders filled.
+fn target(path: &str, template: &str, msg: &Msg, extension: &str) -> io::Result<PathBuf> {
+    let expanded = expand_home(path);
+    if path.ends_with('/') || expanded.is_dir() {
+        new_file(&expanded, &file_name(template, msg, extension))
+    } else {
+        let path = expand_home(&render_template(path, msg));
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(path)
+    }
+}
+
+/// Path of a file which does not exist yet in the directory, creating the subdirectories
+/// the name may have, like `alice/7.eml` for the template `{sender}/{id}`.
+fn new_file(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let path = dir.join(name);
+    let dir = path.parent().unwrap_or(dir);
+    fs::create_dir_all(dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    Ok(unique_path(dir, &name))
+}
+
+/// Save the raw message as an `.eml` file in the directory, without overwriting existing
+/// files, and return the path of the written file.
+pub fn save_eml(dir: &Path, template: &str, msg: &Msg, raw: &str) -> io::Result<PathBuf> {
+    let path = new_file(dir, &file_name(template, msg, "eml"))?;
+    fs::write(&path, raw)?;
+    Ok(path)
+}
+
+/// Export the raw messages with their envelopes, and return the paths written.
+pub fn export(
+    format: Format,
+    path: &str,
+    template: &str,
+    messages: &[(Msg, String)],
+) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for (msg, raw) in messages {
+        let path = match (format, paths.first()) {
+            // all messages go to the same mbox
+            (Format::Mbox, Some(first)) => first.clone(),
+            _ => match target(path, template, msg, format.extension())? {
+                // several messages given the same name
+                path if paths.contains(&path) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    unique_path(path.parent().unwrap_or(Path::new("")), &name)
+                }
+                path => path,
+            },
+        };
+        match format {
+            Format::Eml => fs::write(&path, raw)?,
+            Format::Mbox => append_mbox(&path, raw)?,
+            Format::Text | Format::Markdown => {
+                let message = Message::parse(raw.as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                fs::write(&path, render(&message, format == Format::Markdown))?;
+            }
+        }
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Append the raw message to an mbox, quoting its `From ` lines like mboxrd.
+fn append_mbox(path: &Path, raw: &str) -> io::Result<()> {
+    let message = Message::parse(raw.as_bytes()).ok();
+    let header = |name: &str| message.as_ref().and_then(|m| m.header(name)).unwrap_or("");
+    let sender = header("Return-Path")
+        .trim_matches(|c| c == '<' || c == '>')
+        .split_whitespace()
+        .next()
+        .or_else(|| {
+            let from = header("From");
+            let addr = from.rsplit('<').next()?.trim_end_matches('>').trim();
+            Some(addr).filter(|addr| addr.contains('@'))
+        })
+        .unwrap_or("MAILER-DAEMON")
+        .to_string();
+    let date = dateparse(header("Date")).unwrap_or_default();
+
+    let mut content = format!("From {} {}\n", sender, asctime(date));
+    for line in raw.replace("\r\n", "\n").lines() {
+        if line.trim_start_matches('>').starts_with("From ") {
+            content.push('>');
+        }
+        content.push_str(line);
+        content.push('\n');
+    }
+    content.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(content.as_bytes())
+}
+
+/// Format seconds since the epoch like the `From ` lines of mbox, in UTC.
+fn asctime(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    let (year, month, day) = civil_date(days);
+    let weekdays = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    let months = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    format!(
+        "{} {} {:2} {:02}:{:02}:{:02} {}",
+        weekdays[days.rem_euclid(7) as usize],
+        months[(month - 1) as usize],
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        year
+    )
+}
+
+/// Render the message as plain text, or as markdown.
+pub fn render(message: &Message, markdown: bool) -> String {
+    let header = |name: &str| message.header(name).unwrap_or_default();
+    let mut text = String::new();
+    if markdown {
+        text.push_str(&format!("# {}\n\n", header("Subject")));
+    }
+    for name in ["From", "To", "Cc", "Date", "Subject"] {
+        match message.header(name) {
+            Some(_) if markdown && name == "Subject" => {}
+            Some(value) if markdown => text.push_str(&format!("- **{}:** {}\n", name, value)),
+            Some(value) => text.push_str(&format!("{}: {}\n", name, value)),
+            None => {}
+        }
+    }
+    text.push('\n');
+
+    let body = match message.body(&PartKind::Plain) {
+        Some(part) if part.kind == PartKind::Html => {
+            html::render(&part.body, &mut Vec::new(), false)
+                .iter()
+                .map(|spans| {
+                    spans
+                        .0
+                        .iter()
+                        .map(|span| span.content.as_ref())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Some(part) => part.body.replace("\r\n", "\n"),
+        None => String::new(),
+    };
+    text.push_str(body.trim_end());
+    text.push('\n');
+
+    if !message.attachments.is_empty() {
+        text.push_str(if markdown {
+            "\n## Attachments\n\n"
+        } else {
+            "\nAttachments:\n"
+        });
+        for attachment in &message.attachments {
+            text.push_str(&format!(
+                "- {} ({})\n",
+                attachment.name(),
+                attachment.size()
+            ));
+        }
+    }
+    text
+}
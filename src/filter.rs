@@ -1,2 +1,23 @@
+/// Commands of the command line, with their description.
+pub const COMMANDS: [(&str, &str); 8] = [
+    (
+        "export",
+        "Export the selected or marked messages, like export mbox ~/archive.mbox",
+    ),
+    ("folder", "Show another folder"),
+    ("follow", "Open a link of the message"),
+    ("move", "Move the selected message to a folder"),
+    (
+        "pipe",
+        "Pipe the message to a shell command, -d decoded, -i interactive",
+    ),
+    ("read", "Read a message by id"),
+    ("search", "Search messages"),
+    (
+        "tag",
+        "Add or remove tags of the selected message, like +work -inbox",
+    ),
+];
+
 #[derive(Debug)]
 pub struct Filter(pub String);
@@ -0,0 +1,435 @@
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+/// Render an HTML document into styled terminal lines.
+///
+/// Link targets are pushed into `links`, and labeled with their index iff `label_links` is on,
+/// the same way links of plain text messages are labeled.
+pub fn render(html: &str, links: &mut Vec<String>, label_links: bool) -> Vec<Spans<'static>> {
+    let mut renderer = Renderer {
+        lines: Vec::new(),
+        line: Vec::new(),
+        styles: Vec::new(),
+        lists: Vec::new(),
+        quote: 0,
+        pre: 0,
+        cell: 0,
+        href: None,
+        space: false,
+        has_text: false,
+        links,
+        label_links,
+    };
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        renderer.text(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = match comment.find("-->") {
+                Some(end) => &comment[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => {
+                // not a tag, treat as text
+                renderer.text(rest);
+                rest = "";
+                break;
+            }
+        };
+        let tag = Tag::parse(&rest[1..end]);
+        rest = &rest[end + 1..];
+
+        if tag.closing {
+            renderer.close(&tag.name);
+        } else if tag.name == "script" || tag.name == "style" || tag.name == "head" {
+            rest = skip_invisible(&tag.name, rest);
+        } else {
+            renderer.open(&tag);
+            if tag.self_closing {
+                renderer.close(&tag.name);
+            }
+        }
+    }
+    renderer.text(rest);
+    renderer.finish()
+}
+
+/// Skip the content of an invisible element, up to its closing tag.
+///
+/// The closing tag of `head` is optional and ends at `<body`, nothing is skipped
+/// when the end cannot be found so a stray tag does not hide the whole document.
+fn skip_invisible<'a>(name: &str, rest: &'a str) -> &'a str {
+    let lower = rest.to_ascii_lowercase();
+    let close = format!("</{}", name);
+    match lower.find(&close) {
+        Some(i) => rest[i..].find('>').map_or("", |end| &rest[i + end + 1..]),
+        None if name == "head" => lower.find("<body").map_or(rest, |i| &rest[i..]),
+        None => rest,
+    }
+}
+
+struct Tag {
+    name: String,
+    attrs: String,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl Tag {
+    fn parse(content: &str) -> Self {
+        let closing = content.starts_with('/');
+        let content = content.trim_start_matches('/');
+        let self_closing = content.ends_with('/');
+        let content = content.trim_end_matches('/');
+        let (name, attrs) = match content.find(char::is_whitespace) {
+            Some(i) => content.split_at(i),
+            None => (content, ""),
+        };
+
+        Tag {
+            name: name.to_lowercase(),
+            attrs: attrs.to_string(),
+            closing,
+            self_closing,
+        }
+    }
+
+    /// Get the value of an attribute.
+    fn attr(&self, name: &str) -> Option<String> {
+        let lower = self.attrs.to_ascii_lowercase();
+        let mut from = 0;
+        while let Some(i) = lower[from..].find(name) {
+            let start = from + i;
+            from = start + name.len();
+            // make sure the match is a whole attribute name
+            let boundary = start == 0 || lower[..start].ends_with(char::is_whitespace);
+            let rest = lower[from..].trim_start();
+            if !boundary || !rest.starts_with('=') {
+                continue;
+            }
+
+            let value = self.attrs[self.attrs.len() - rest.len() + 1..].trim_start();
+            let value = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+                _ => value.split(char::is_whitespace).next().unwrap_or(""),
+            };
+            return Some(decode_entities(value));
+        }
+        None
+    }
+}
+
+struct Renderer<'a> {
+    lines: Vec<Spans<'static>>,
+    line: Vec<Span<'static>>,
+    /// styles of the opened tags
+    styles: Vec<(String, Style)>,
+    /// opened lists, with the index of the next item for ordered ones
+    lists: Vec<Option<usize>>,
+    quote: usize,
+    pre: usize,
+    /// index of the next cell in the current table row
+    cell: usize,
+    href: Option<String>,
+    /// whether a space is pending before the next word
+    space: bool,
+    /// whether the current line has content apart from decorations
+    has_text: bool,
+    links: &'a mut Vec<String>,
+    label_links: bool,
+}
+
+impl Renderer<'_> {
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, (_, s)| style.patch(*s))
+    }
+
+    fn open(&mut self, tag: &Tag) {
+        let style = Style::default();
+        match tag.name.as_str() {
+            "br" => self.new_line(),
+            "p" | "div" => self.paragraph(),
+            "hr" => {
+                self.block();
+                self.push("─".repeat(40), Style::default().fg(Color::DarkGray));
+                self.block();
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                let level = tag.name[1..].parse().unwrap_or(1);
+                let mut style = style.fg(Color::Magenta).add_modifier(Modifier::BOLD);
+                if level == 1 {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                self.styles.push((tag.name.clone(), style));
+                self.decorate(format!("{} ", "#".repeat(level)), self.style());
+            }
+            "b" | "strong" => self
+                .styles
+                .push((tag.name.clone(), style.add_modifier(Modifier::BOLD))),
+            "i" | "em" | "cite" => self
+                .styles
+                .push((tag.name.clone(), style.add_modifier(Modifier::ITALIC))),
+            "u" | "ins" => self
+                .styles
+                .push((tag.name.clone(), style.add_modifier(Modifier::UNDERLINED))),
+            "s" | "strike" | "del" => self
+                .styles
+                .push((tag.name.clone(), style.add_modifier(Modifier::CROSSED_OUT))),
+            "code" | "tt" | "kbd" => self
+                .styles
+                .push((tag.name.clone(), style.fg(Color::Yellow))),
+            "pre" => {
+                self.paragraph();
+                self.pre += 1;
+                self.styles
+                    .push((tag.name.clone(), style.fg(Color::Yellow)));
+            }
+            "ul" | "ol" => {
+                self.block();
+                self.lists
+                    .push(if tag.name == "ol" { Some(1) } else { None });
+            }
+            "li" => {
+                self.block();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(index)) => {
+                        *index += 1;
+                        format!("{}{}. ", indent, *index - 1)
+                    }
+                    _ => format!("{}• ", indent),
+                };
+                self.decorate(bullet, Style::default());
+            }
+            "blockquote" => {
+                self.paragraph();
+                self.quote += 1;
+            }
+            "tr" => {
+                self.block();
+                self.cell = 0;
+            }
+            "td" | "th" => {
+                if self.cell > 0 {
+                    self.decorate(" │ ".to_string(), Style::default().fg(Color::DarkGray));
+                }
+                self.cell += 1;
+                if tag.name == "th" {
+                    self.styles
+                        .push((tag.name.clone(), style.add_modifier(Modifier::BOLD)));
+                }
+            }
+            "a" => {
+                self.href = tag.attr("href").filter(|href| !href.starts_with('#'));
+                if self.href.is_some() {
+                    self.styles.push((
+                        tag.name.clone(),
+                        style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+                    ));
+                }
+            }
+            "img" => {
+                if let Some(alt) = tag.attr("alt").filter(|alt| !alt.is_empty()) {
+                    self.push(format!("[{}]", alt), Style::default().fg(Color::DarkGray));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        // pop the style of the tag, along with any tag not closed inside it
+        if let Some(i) = self.styles.iter().rposition(|(tag, _)| tag == name) {
+            self.styles.truncate(i);
+        }
+
+        match name {
+            "p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.paragraph(),
+            "pre" => {
+                self.pre = self.pre.saturating_sub(1);
+                self.paragraph();
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block();
+            }
+            "li" | "tr" | "table" => self.block(),
+            "blockquote" => {
+                self.block();
+                self.quote = self.quote.saturating_sub(1);
+                self.paragraph();
+            }
+            "a" => {
+                if let Some(href) = self.href.take() {
+                    self.links.push(href.clone());
+                    let label = if self.label_links {
+                        format!(" <{}> [{}]", href, self.links.len())
+                    } else {
+                        format!(" <{}>", href)
+                    };
+                    self.push(label, Style::default().fg(Color::Cyan));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Add text content, collapsing whitespaces outside of preformatted blocks.
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let text = decode_entities(text);
+        let style = self.style();
+
+        if self.pre > 0 {
+            let mut lines = text.split('\n');
+            if let Some(first) = lines.next() {
+                self.push(first.to_string(), style);
+            }
+            for line in lines {
+                self.new_line();
+                self.push(line.to_string(), style);
+            }
+            return;
+        }
+
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        for (i, word) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                self.space = true;
+            }
+            let word = if self.space && self.has_text && !self.ends_with_space() {
+                format!(" {}", word)
+            } else {
+                word.to_string()
+            };
+            self.push(word, style);
+            self.space = false;
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space = true;
+        }
+    }
+
+    fn push(&mut self, content: String, style: Style) {
+        self.decorate(content, style);
+        self.has_text = true;
+    }
+
+    /// Add content which does not count as text of the line, like list bullets.
+    fn decorate(&mut self, content: String, style: Style) {
+        if self.line.is_empty() && self.quote > 0 {
+            self.line.push(Span::styled(
+                "│ ".repeat(self.quote),
+                Style::default().fg(Color::Green),
+            ));
+        }
+        self.line.push(Span::styled(content, style));
+    }
+
+    fn ends_with_space(&self) -> bool {
+        self.line
+            .last()
+            .is_some_and(|span| span.content.ends_with(' '))
+    }
+
+    fn new_line(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        self.lines.push(Spans::from(line));
+        self.space = false;
+        self.has_text = false;
+    }
+
+    /// Start a new line unless the current line has no text.
+    fn block(&mut self) {
+        if self.has_text {
+            self.new_line();
+        }
+    }
+
+    /// Start a new line separated from the previous content by a blank line.
+    fn paragraph(&mut self) {
+        self.block();
+        if self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Spans::default());
+        }
+    }
+
+    fn finish(mut self) -> Vec<Spans<'static>> {
+        self.block();
+        while self.lines.last().is_some_and(|line| line.width() == 0) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+/// Decode HTML character references.
+pub fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "copy" => Some('©'),
+                "reg" => Some('®'),
+                "hellip" => Some('…'),
+                "mdash" => Some('—'),
+                "ndash" => Some('–'),
+                "laquo" => Some('«'),
+                "raquo" => Some('»'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                "bull" => Some('•'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                    .and_then(|code| code.ok())
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                output.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
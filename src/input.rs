@@ -0,0 +1,257 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Maximum number of commands kept in history.
+const HISTORY_SIZE: usize = 1000;
+
+/// Editable command line with history and completion.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    /// cursor position, in chars
+    cursor: usize,
+    history: Vec<String>,
+    /// position in history while browsing it, with the line being edited before
+    browsing: Option<(usize, String)>,
+    history_path: Option<PathBuf>,
+    completion: Option<Completion>,
+}
+
+/// Candidates of the word being completed, cycled with each completion.
+#[derive(Debug)]
+struct Completion {
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl LineEditor {
+    /// Create a line editor with history persisted in the given file.
+    pub fn with_history(path: PathBuf) -> Self {
+        let history = fs::read_to_string(&path)
+            .map(|content| content.lines().map(|line| line.to_string()).collect())
+            .unwrap_or_default();
+        LineEditor {
+            history,
+            history_path: Some(path),
+            ..LineEditor::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Cursor position, in chars.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.chars().count();
+        self.browsing = None;
+        self.completion = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Byte index of a char position.
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let i = self.byte_index(self.cursor);
+        self.text.insert(i, c);
+        self.cursor += 1;
+        self.completion = None;
+    }
+
+    /// Delete the char before the cursor.
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let i = self.byte_index(self.cursor);
+            self.text.remove(i);
+        }
+        self.completion = None;
+    }
+
+    /// Delete the char under the cursor.
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let i = self.byte_index(self.cursor);
+            self.text.remove(i);
+        }
+        self.completion = None;
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.completion = None;
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+        self.completion = None;
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+        self.completion = None;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.chars().count();
+        self.completion = None;
+    }
+
+    /// Delete the word before the cursor, like Ctrl-W of shells.
+    pub fn delete_word(&mut self) {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1] == ' ' {
+            start -= 1;
+        }
+        while start > 0 && chars[start - 1] != ' ' {
+            start -= 1;
+        }
+        self.delete_range(start, self.cursor);
+    }
+
+    /// Delete everything before the cursor, like Ctrl-U of shells.
+    pub fn delete_to_start(&mut self) {
+        self.delete_range(0, self.cursor);
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let (i, j) = (self.byte_index(start), self.byte_index(end));
+        self.text.replace_range(i..j, "");
+        self.cursor = start;
+        self.completion = None;
+    }
+
+    /// Recall the previous command of history.
+    pub fn history_prev(&mut self) {
+        let index = match &self.browsing {
+            Some((0, _)) => return,
+            Some((index, _)) => index - 1,
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        let edited = match self.browsing.take() {
+            Some((_, edited)) => edited,
+            None => self.text.clone(),
+        };
+        self.set(&self.history[index].clone());
+        self.browsing = Some((index, edited));
+    }
+
+    /// Recall the next command of history, or the line edited before browsing it.
+    pub fn history_next(&mut self) {
+        if let Some((index, edited)) = self.browsing.take() {
+            if index + 1 < self.history.len() {
+                self.set(&self.history[index + 1].clone());
+                self.browsing = Some((index + 1, edited));
+            } else {
+                self.set(&edited);
+            }
+        }
+    }
+
+    /// Add the current line to history, and append it to the history file.
+    pub fn commit(&mut self) -> io::Result<()> {
+        let line = self.text.trim().to_string();
+        self.browsing = None;
+        if line.is_empty() || self.history.last() == Some(&line) {
+            return Ok(());
+        }
+
+        self.history.push(line.clone());
+        if let Some(path) = &self.history_path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            if self.history.len() > HISTORY_SIZE {
+                self.history.drain(..self.history.len() - HISTORY_SIZE);
+                fs::write(path, self.history.join("\n") + "\n")?;
+            } else {
+                let mut file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                writeln!(file, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the words before the cursor, the last one being the word to complete.
+    pub fn words_before_cursor(&self) -> Vec<&str> {
+        let end = match &self.completion {
+            // complete the original word, not the current candidate
+            Some(completion) => completion.start,
+            None => self.cursor,
+        };
+        self.text[..self.byte_index(end)].split(' ').collect()
+    }
+
+    /// Complete the word before the cursor with the candidates starting with it,
+    /// cycling through them on successive calls.
+    pub fn complete(&mut self, candidates: Vec<String>) {
+        if let Some(mut completion) = self.completion.take() {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            let candidate = completion.candidates[completion.index].clone();
+            self.replace_word(completion.start, &candidate);
+            self.completion = Some(completion);
+            return;
+        }
+
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1] != ' ' {
+            start -= 1;
+        }
+        let word: String = chars[start..self.cursor]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+
+        // quoted candidates match the word with or without its opening quote
+        let word = word.trim_start_matches('"');
+        let mut candidates: Vec<String> = candidates
+            .into_iter()
+            .filter(|c| c.to_lowercase().trim_start_matches('"').starts_with(word))
+            .collect();
+        candidates.dedup();
+        if candidates.is_empty() {
+            return;
+        }
+
+        // the original word is kept as the last candidate to cycle back to it
+        let original: String = chars[start..self.cursor].iter().collect();
+        candidates.push(original);
+        self.replace_word(start, &candidates[0].clone());
+        self.completion = Some(Completion {
+            start,
+            candidates,
+            index: 0,
+        });
+    }
+
+    /// Replace the text between the start and the cursor.
+    fn replace_word(&mut self, start: usize, word: &str) {
+        let (i, j) = (self.byte_index(start), self.byte_index(self.cursor));
+        self.text.replace_range(i..j, word);
+        self.cursor = start + word.chars().count();
+    }
+}
@@ -29,6 +29,23 @@ impl Keymap {
                         ),
                         Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::CancelFilter),
                         Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::ReviewMsg),
+                        Keybind(KeyCode::Char('m'), KeyModifiers::NONE, Event::Compose),
+                        Keybind(KeyCode::Char('u'), KeyModifiers::NONE, Event::ToggleSeen),
+                        Keybind(KeyCode::Char('f'), KeyModifiers::NONE, Event::ToggleFlagged),
+                        Keybind(KeyCode::Char('e'), KeyModifiers::NONE, Event::ExportMessage),
+                        Keybind(KeyCode::Char(' '), KeyModifiers::NONE, Event::ToggleMark),
+                        Keybind(KeyCode::Char('|'), KeyModifiers::NONE, Event::PipeMessage),
+                        Keybind(
+                            KeyCode::Char('y'),
+                            KeyModifiers::NONE,
+                            Event::SwitchMode(KeyMode::Yank),
+                        ),
+                        Keybind(
+                            KeyCode::Char('p'),
+                            KeyModifiers::CONTROL,
+                            Event::OpenPalette,
+                        ),
+                        Keybind(KeyCode::Char('?'), KeyModifiers::NONE, Event::ShowHelp),
                     ],
                 ),
                 (
@@ -38,6 +55,27 @@ impl Keymap {
                         Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
                         Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::Submit),
                         Keybind(KeyCode::Backspace, KeyModifiers::NONE, Event::Backspace),
+                        Keybind(KeyCode::Delete, KeyModifiers::NONE, Event::Delete),
+                        Keybind(KeyCode::Left, KeyModifiers::NONE, Event::CursorLeft),
+                        Keybind(KeyCode::Right, KeyModifiers::NONE, Event::CursorRight),
+                        Keybind(KeyCode::Home, KeyModifiers::NONE, Event::CursorStart),
+                        Keybind(KeyCode::End, KeyModifiers::NONE, Event::CursorEnd),
+                        Keybind(
+                            KeyCode::Char('a'),
+                            KeyModifiers::CONTROL,
+                            Event::CursorStart,
+                        ),
+                        Keybind(KeyCode::Char('e'), KeyModifiers::CONTROL, Event::CursorEnd),
+                        Keybind(KeyCode::Char('w'), KeyModifiers::CONTROL, Event::DeleteWord),
+                        Keybind(
+                            KeyCode::Char('u'),
+                            KeyModifiers::CONTROL,
+                            Event::DeleteToStart,
+                        ),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::HistoryPrev),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::HistoryNext),
+                        Keybind(KeyCode::Tab, KeyModifiers::NONE, Event::Complete),
+                        Keybind(KeyCode::Char('y'), KeyModifiers::CONTROL, Event::Paste),
                     ],
                 ),
                 (
@@ -48,6 +86,198 @@ impl Keymap {
                         Keybind(KeyCode::Char('k'), KeyModifiers::NONE, Event::ScrollUp),
                         Keybind(KeyCode::Char('f'), KeyModifiers::NONE, Event::ShowLinks),
                         Keybind(KeyCode::Char('s'), KeyModifiers::NONE, Event::ShowStats),
+                        Keybind(KeyCode::Char('e'), KeyModifiers::NONE, Event::ExportMessage),
+                        Keybind(KeyCode::Char('h'), KeyModifiers::NONE, Event::ToggleHeaders),
+                        Keybind(KeyCode::Char('t'), KeyModifiers::NONE, Event::TogglePart),
+                        Keybind(KeyCode::Char('r'), KeyModifiers::NONE, Event::ToggleSource),
+                        Keybind(KeyCode::Char('z'), KeyModifiers::NONE, Event::ToggleFold),
+                        Keybind(KeyCode::Tab, KeyModifiers::NONE, Event::NextAttachment),
+                        Keybind(
+                            KeyCode::Char('a'),
+                            KeyModifiers::NONE,
+                            Event::SaveAttachment,
+                        ),
+                        Keybind(
+                            KeyCode::Char('A'),
+                            KeyModifiers::SHIFT,
+                            Event::SaveAllAttachments,
+                        ),
+                        Keybind(
+                            KeyCode::Char('o'),
+                            KeyModifiers::NONE,
+                            Event::OpenAttachment,
+                        ),
+                        Keybind(
+                            KeyCode::Char('y'),
+                            KeyModifiers::NONE,
+                            Event::SwitchMode(KeyMode::Yank),
+                        ),
+                        Keybind(KeyCode::Char('/'), KeyModifiers::NONE, Event::StartSearch),
+                        Keybind(KeyCode::Char('n'), KeyModifiers::NONE, Event::NextMatch),
+                        Keybind(KeyCode::Char('N'), KeyModifiers::SHIFT, Event::PrevMatch),
+                        Keybind(KeyCode::Char('v'), KeyModifiers::NONE, Event::StartVisual),
+                        Keybind(KeyCode::Char('|'), KeyModifiers::NONE, Event::PipeMessage),
+                        Keybind(
+                            KeyCode::Char('p'),
+                            KeyModifiers::CONTROL,
+                            Event::OpenPalette,
+                        ),
+                        Keybind(KeyCode::Char('?'), KeyModifiers::NONE, Event::ShowHelp),
+                    ],
+                ),
+                (
+                    KeyMode::Compose,
+                    vec![
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('e'), KeyModifiers::NONE, Event::EditDraft),
+                        Keybind(KeyCode::Char('a'), KeyModifiers::NONE, Event::AttachFile),
+                        Keybind(
+                            KeyCode::Char('d'),
+                            KeyModifiers::NONE,
+                            Event::RemoveAttachment,
+                        ),
+                        Keybind(
+                            KeyCode::Char('j'),
+                            KeyModifiers::NONE,
+                            Event::NextAttachment,
+                        ),
+                        Keybind(
+                            KeyCode::Char('k'),
+                            KeyModifiers::NONE,
+                            Event::PrevAttachment,
+                        ),
+                        Keybind(KeyCode::Char('s'), KeyModifiers::NONE, Event::SendDraft),
+                        Keybind(
+                            KeyCode::Char('p'),
+                            KeyModifiers::CONTROL,
+                            Event::OpenPalette,
+                        ),
+                        Keybind(KeyCode::Char('?'), KeyModifiers::NONE, Event::ShowHelp),
+                    ],
+                ),
+                (
+                    KeyMode::Picker,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::Submit),
+                        Keybind(KeyCode::Backspace, KeyModifiers::NONE, Event::Backspace),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Char('n'), KeyModifiers::CONTROL, Event::SelectNext),
+                        Keybind(KeyCode::Char('p'), KeyModifiers::CONTROL, Event::SelectPrev),
+                    ],
+                ),
+                (
+                    KeyMode::Links,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::OpenLink),
+                        Keybind(KeyCode::Char('o'), KeyModifiers::NONE, Event::OpenLink),
+                        Keybind(KeyCode::Char('c'), KeyModifiers::NONE, Event::CopyLink),
+                        Keybind(KeyCode::Char('y'), KeyModifiers::NONE, Event::YankLink),
+                        Keybind(KeyCode::Char('j'), KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Char('k'), KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Backspace, KeyModifiers::NONE, Event::Backspace),
+                    ],
+                ),
+                (
+                    KeyMode::Yank,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(
+                            KeyCode::Char('f'),
+                            KeyModifiers::NONE,
+                            Event::Yank(YankField::Sender),
+                        ),
+                        Keybind(
+                            KeyCode::Char('s'),
+                            KeyModifiers::NONE,
+                            Event::Yank(YankField::Subject),
+                        ),
+                        Keybind(
+                            KeyCode::Char('d'),
+                            KeyModifiers::NONE,
+                            Event::Yank(YankField::Date),
+                        ),
+                        Keybind(
+                            KeyCode::Char('i'),
+                            KeyModifiers::NONE,
+                            Event::Yank(YankField::MessageId),
+                        ),
+                        Keybind(KeyCode::Char('l'), KeyModifiers::NONE, Event::ShowLinks),
+                    ],
+                ),
+                (
+                    KeyMode::Find,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::Submit),
+                        Keybind(KeyCode::Backspace, KeyModifiers::NONE, Event::Backspace),
+                        Keybind(KeyCode::Delete, KeyModifiers::NONE, Event::Delete),
+                        Keybind(KeyCode::Left, KeyModifiers::NONE, Event::CursorLeft),
+                        Keybind(KeyCode::Right, KeyModifiers::NONE, Event::CursorRight),
+                        Keybind(KeyCode::Home, KeyModifiers::NONE, Event::CursorStart),
+                        Keybind(KeyCode::End, KeyModifiers::NONE, Event::CursorEnd),
+                        Keybind(KeyCode::Char('w'), KeyModifiers::CONTROL, Event::DeleteWord),
+                        Keybind(
+                            KeyCode::Char('u'),
+                            KeyModifiers::CONTROL,
+                            Event::DeleteToStart,
+                        ),
+                    ],
+                ),
+                (
+                    KeyMode::Visual,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('v'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('j'), KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Char('k'), KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Char('y'), KeyModifiers::NONE, Event::YankSelection),
+                        Keybind(KeyCode::Char('r'), KeyModifiers::NONE, Event::QuoteReply),
+                    ],
+                ),
+                (
+                    KeyMode::Help,
+                    vec![
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('?'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('j'), KeyModifiers::NONE, Event::ScrollDown),
+                        Keybind(KeyCode::Char('k'), KeyModifiers::NONE, Event::ScrollUp),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::ScrollDown),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::ScrollUp),
+                    ],
+                ),
+                (
+                    KeyMode::Output,
+                    vec![
+                        Keybind(KeyCode::Char('q'), KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Char('j'), KeyModifiers::NONE, Event::ScrollDown),
+                        Keybind(KeyCode::Char('k'), KeyModifiers::NONE, Event::ScrollUp),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::ScrollDown),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::ScrollUp),
+                    ],
+                ),
+                (
+                    KeyMode::Palette,
+                    vec![
+                        Keybind(KeyCode::Esc, KeyModifiers::NONE, Event::Quit),
+                        Keybind(KeyCode::Enter, KeyModifiers::NONE, Event::Submit),
+                        Keybind(KeyCode::Backspace, KeyModifiers::NONE, Event::Backspace),
+                        Keybind(KeyCode::Down, KeyModifiers::NONE, Event::SelectNext),
+                        Keybind(KeyCode::Up, KeyModifiers::NONE, Event::SelectPrev),
+                        Keybind(KeyCode::Char('n'), KeyModifiers::CONTROL, Event::SelectNext),
+                        Keybind(KeyCode::Char('p'), KeyModifiers::CONTROL, Event::SelectPrev),
                     ],
                 ),
             ]),
@@ -63,16 +293,29 @@ impl Keymap {
             }
         }
 
-        // if input mode is KeyMode::Input, map all chars into RawInput
-        if self.mode == KeyMode::Insert {
+        // if input mode is KeyMode::Input, KeyMode::Picker, KeyMode::Palette,
+        // KeyMode::Links or KeyMode::Find, map all typed chars into RawInput
+        if matches!(
+            self.mode,
+            KeyMode::Insert | KeyMode::Picker | KeyMode::Palette | KeyMode::Links | KeyMode::Find
+        ) {
             if let KeyCode::Char(c) = key.code {
-                events.push(Event::RawInput(c));
+                if key.modifiers == KeyModifiers::NONE || key.modifiers == KeyModifiers::SHIFT {
+                    events.push(Event::RawInput(c));
+                }
             }
         }
 
         events
     }
 
+    /// Keybinds of the given mode.
+    pub fn keybinds(&self, mode: &KeyMode) -> &[Keybind] {
+        self.keybinds
+            .get(mode)
+            .map_or(&[], |keybinds| keybinds.as_slice())
+    }
+
     pub fn switch_to(&mut self, mode: KeyMode) {
         self.prev_mode = self.mode.clone();
         self.mode = mode;
@@ -90,6 +333,15 @@ pub enum KeyMode {
     Motion,
     Insert,
     Review,
+    Compose,
+    Picker,
+    Palette,
+    Help,
+    Links,
+    Yank,
+    Find,
+    Visual,
+    Output,
 }
 
 impl fmt::Display for KeyMode {
@@ -101,6 +353,15 @@ impl fmt::Display for KeyMode {
                 KeyMode::Insert => "INSERT",
                 KeyMode::Motion => "MOTION",
                 KeyMode::Review => "REVIEW",
+                KeyMode::Compose => "COMPOSE",
+                KeyMode::Picker => "PICKER",
+                KeyMode::Palette => "PALETTE",
+                KeyMode::Help => "HELP",
+                KeyMode::Links => "LINKS",
+                KeyMode::Yank => "YANK",
+                KeyMode::Find => "FIND",
+                KeyMode::Visual => "VISUAL",
+                KeyMode::Output => "OUTPUT",
             }
         )
     }
@@ -117,9 +378,45 @@ impl Keybind {
             None
         }
     }
+
+    pub fn event(&self) -> &Event {
+        &self.2
+    }
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for Keybind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.1.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.1.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        match self.0 {
+            // shift is already given by the uppercase char
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift-Tab"),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Event {
     ExitApp,
     SelectNextMsg,
@@ -135,4 +432,128 @@ pub enum Event {
     ScrollDown,
     ShowLinks,
     ShowStats,
+    ToggleHeaders,
+    TogglePart,
+    ToggleSource,
+    ToggleFold,
+    NextAttachment,
+    SaveAttachment,
+    SaveAllAttachments,
+    OpenAttachment,
+    Compose,
+    EditDraft,
+    AttachFile,
+    RemoveAttachment,
+    PrevAttachment,
+    SendDraft,
+    SelectNext,
+    SelectPrev,
+    Delete,
+    CursorLeft,
+    CursorRight,
+    CursorStart,
+    CursorEnd,
+    DeleteWord,
+    DeleteToStart,
+    HistoryPrev,
+    HistoryNext,
+    Complete,
+    OpenPalette,
+    ShowHelp,
+    OpenLink,
+    CopyLink,
+    YankLink,
+    Paste,
+    Yank(YankField),
+    StartSearch,
+    NextMatch,
+    PrevMatch,
+    StartVisual,
+    YankSelection,
+    QuoteReply,
+    ToggleSeen,
+    ToggleFlagged,
+    ExportMessage,
+    ToggleMark,
+    PipeMessage,
+}
+
+/// Field of the selected message copied to the clipboard.
+#[derive(Debug, Clone, PartialEq)]
+pub enum YankField {
+    Sender,
+    Subject,
+    Date,
+    MessageId,
+}
+
+impl Event {
+    /// Short description of what the event does, for the command palette.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Event::ExitApp => "Quit himalaya-tui",
+            Event::SelectNextMsg => "Select the next message",
+            Event::SelectPrevMsg => "Select the previous message",
+            Event::ReviewMsg => "Read the selected message",
+            Event::Quit => "Close the current view",
+            Event::Submit => "Confirm",
+            Event::RawInput(_) => "Type a char",
+            Event::Backspace => "Delete the previous char",
+            Event::CancelFilter => "Cancel the last search",
+            Event::SwitchMode(KeyMode::Insert) => "Open the command line",
+            Event::SwitchMode(KeyMode::Yank) => "Copy a field of the message",
+            Event::SwitchMode(_) => "Switch mode",
+            Event::ScrollUp => "Scroll up",
+            Event::ScrollDown => "Scroll down",
+            Event::ShowLinks => "Pick a link of the message",
+            Event::ShowStats => "Show statistics of the message",
+            Event::ToggleHeaders => "Toggle all headers",
+            Event::TogglePart => "Switch between plain text and HTML",
+            Event::ToggleSource => "Toggle the raw source",
+            Event::ToggleFold => "Fold quotes and signature",
+            Event::NextAttachment => "Select the next attachment",
+            Event::SaveAttachment => "Save the selected attachment",
+            Event::SaveAllAttachments => "Save all attachments",
+            Event::OpenAttachment => "Open the selected attachment",
+            Event::Compose => "Write a new message",
+            Event::EditDraft => "Edit the draft",
+            Event::AttachFile => "Attach a file",
+            Event::RemoveAttachment => "Remove the selected attachment",
+            Event::PrevAttachment => "Select the previous attachment",
+            Event::SendDraft => "Send the draft",
+            Event::SelectNext => "Select the next entry",
+            Event::SelectPrev => "Select the previous entry",
+            Event::Delete => "Delete the char under the cursor",
+            Event::CursorLeft => "Move the cursor left",
+            Event::CursorRight => "Move the cursor right",
+            Event::CursorStart => "Move the cursor to the start",
+            Event::CursorEnd => "Move the cursor to the end",
+            Event::DeleteWord => "Delete the previous word",
+            Event::DeleteToStart => "Delete to the start of the line",
+            Event::HistoryPrev => "Recall the previous command",
+            Event::HistoryNext => "Recall the next command",
+            Event::Complete => "Complete the word",
+            Event::OpenPalette => "Search actions",
+            Event::ShowHelp => "Show keybindings",
+            Event::OpenLink => "Open the selected link",
+            Event::CopyLink => "Copy the selected link to the clipboard",
+            Event::YankLink => "Yank the selected link, to paste it in the command line",
+            Event::Paste => "Paste the yanked text",
+            Event::Yank(YankField::Sender) => "Copy the sender",
+            Event::Yank(YankField::Subject) => "Copy the subject",
+            Event::Yank(YankField::Date) => "Copy the date",
+            Event::Yank(YankField::MessageId) => "Copy the message id",
+            Event::StartSearch => "Search in the message",
+            Event::NextMatch => "Go to the next match",
+            Event::PrevMatch => "Go to the previous match",
+            Event::StartVisual => "Select lines of the message",
+            Event::YankSelection => "Copy the selected lines",
+            Event::QuoteReply => "Reply quoting the selected lines",
+            Event::ToggleSeen => "Mark the selected message as seen or unseen",
+            Event::ToggleFlagged => "Flag or unflag the selected message",
+            Event::ExportMessage => "Save the message as an .eml file",
+            Event::ToggleMark => "Mark the selected message for export",
+            Event::PipeMessage => "Pipe the message to a shell command",
+        }
+    }
 }
@@ -0,0 +1,21 @@
+pub mod app;
+pub mod backend;
+pub mod cache;
+pub mod clipboard;
+pub mod compose;
+pub mod config;
+pub mod export;
+pub mod filter;
+pub mod html;
+pub mod input;
+pub mod keymap;
+pub mod links;
+pub mod mail;
+pub mod message;
+pub mod notify;
+pub mod palette;
+pub mod pipe;
+pub mod ui;
+pub mod utils;
+pub mod version;
+pub mod watch;
@@ -0,0 +1,92 @@
+/// Chars of hint labels, leaving out the keys of the picker actions.
+const HINT_CHARS: &[char] = &['a', 's', 'd', 'f', 'g', 'h', 'l', 'w', 'e', 'r', 't', 'u'];
+
+/// Popup listing the links of a message, selected by hint labels like vimium.
+#[derive(Debug)]
+pub struct LinkPicker {
+    pub links: Vec<String>,
+    pub labels: Vec<String>,
+    /// chars of the label typed so far
+    pub typed: String,
+    pub selected: usize,
+}
+
+impl LinkPicker {
+    pub fn new(links: Vec<String>) -> Self {
+        let labels = hint_labels(links.len());
+        LinkPicker {
+            links,
+            labels,
+            typed: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_hint(c: char) -> bool {
+        HINT_CHARS.contains(&c)
+    }
+
+    /// Type a char of a label, selecting the link once its label is complete.
+    pub fn push(&mut self, c: char) {
+        self.typed.push(c);
+        if !self
+            .labels
+            .iter()
+            .any(|label| label.starts_with(&self.typed))
+        {
+            // start a new label from this char
+            self.typed = c.to_string();
+            if !self
+                .labels
+                .iter()
+                .any(|label| label.starts_with(&self.typed))
+            {
+                self.typed.clear();
+            }
+        }
+        if let Some(index) = self.labels.iter().position(|label| *label == self.typed) {
+            self.selected = index;
+            self.typed.clear();
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.typed.pop();
+    }
+
+    pub fn next(&mut self) {
+        if !self.links.is_empty() {
+            self.selected = (self.selected + 1) % self.links.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let size = self.links.len();
+        if size != 0 {
+            self.selected = (self.selected + size - 1) % size;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.links.get(self.selected).map(|link| link.as_str())
+    }
+}
+
+/// Labels of the same length for the given number of links, so that none is a prefix of another.
+fn hint_labels(count: usize) -> Vec<String> {
+    let base = HINT_CHARS.len();
+    let mut length = 1;
+    while base.pow(length as u32) < count {
+        length += 1;
+    }
+    (0..count)
+        .map(|mut index| {
+            let mut label = vec![HINT_CHARS[0]; length];
+            for c in label.iter_mut().rev() {
+                *c = HINT_CHARS[index % base];
+                index /= base;
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+use std::{fs, io, path::PathBuf};
+
+use super::{matches, parse_envelope, read_only, MailBackend};
+use crate::app::data::{Flag, Msg};
+
+/// Read-only backend showing a single message file, with id 1.
+#[derive(Debug)]
+pub struct Eml {
+    path: PathBuf,
+}
+
+impl Eml {
+    pub fn new(path: PathBuf) -> Self {
+        Eml { path }
+    }
+}
+
+impl MailBackend for Eml {
+    fn folders(&self) -> io::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn envelopes(&self) -> io::Result<Vec<Msg>> {
+        Ok(vec![parse_envelope(
+            1,
+            &fs::read(&self.path)?,
+            vec![Flag::Seen],
+        )?])
+    }
+
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>> {
+        Ok(self
+            .envelopes()?
+            .into_iter()
+            .filter(|msg| matches(msg, query))
+            .collect())
+    }
+
+    fn read(&self, id: usize) -> io::Result<String> {
+        if id != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no message {}", id),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&fs::read(&self.path)?).to_string())
+    }
+
+    fn add_flag(&self, _id: usize, _flag: &Flag) -> io::Result<()> {
+        Err(read_only("message file"))
+    }
+
+    fn remove_flag(&self, _id: usize, _flag: &Flag) -> io::Result<()> {
+        Err(read_only("message file"))
+    }
+
+    fn move_to(&self, _id: usize, _folder: &str) -> io::Result<()> {
+        Err(read_only("message file"))
+    }
+}
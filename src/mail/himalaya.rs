@@ -0,0 +1,173 @@
+use std::io;
+
+use serde_json::Value;
+
+use super::{flag_name, phrases, MailBackend};
+use crate::{
+    app::data::{parse_envelopes, Flag, Msg},
+    config::Config,
+    utils::{get_email_list, get_folders, himalaya, himalaya_command, output, response},
+    version::Version,
+};
+
+/// Backend running himalaya for each operation.
+#[derive(Debug)]
+pub struct Himalaya {
+    config: Config,
+    version: Option<Version>,
+}
+
+impl Himalaya {
+    pub fn new(config: Config, version: Option<Version>) -> Self {
+        Himalaya { config, version }
+    }
+
+    /// Run the subcommand with its arguments on the folder of the backend.
+    fn run(&self, subcommand: &[&str], args: &[&str]) -> io::Result<String> {
+        output(himalaya(&self.config, self.version, subcommand, true).args(args))
+    }
+}
+
+fn invalid_data<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+impl MailBackend for Himalaya {
+    fn folders(&self) -> io::Result<Vec<String>> {
+        get_folders(&self.config, self.version)
+    }
+
+    fn envelopes(&self) -> io::Result<Vec<Msg>> {
+        parse_envelopes(&get_email_list(&self.config, self.version)?).map_err(invalid_data)
+    }
+
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>> {
+        let output = match self.version {
+            Some(version) if version >= Version::new(1, 0, 0) => {
+                let filter = filter_query(&phrases(query));
+                let filter: Vec<&str> = filter.iter().map(|arg| arg.as_str()).collect();
+                self.run(version.list_args(), &filter)?
+            }
+            _ => {
+                let mut command = vec!["search".to_string()];
+                command.extend(query.iter().cloned());
+                himalaya_command(&self.config, command)?
+            }
+        };
+        parse_envelopes(&output).map_err(invalid_data)
+    }
+
+    fn read(&self, id: usize) -> io::Result<String> {
+        let id = id.to_string();
+        if self.version >= Some(Version::new(1, 0, 0)) {
+            // the full message is exported as is, whatever the output format
+            return self.run(&["message", "export"], &[&id, "--full"]);
+        }
+        let output = self.run(&["read"], &[&id, "--raw"])?;
+        let value: Value = serde_json::from_str(&output)?;
+        response(&value)
+            .as_str()
+            .map(|raw| raw.to_string())
+            .ok_or_else(|| invalid_data("message is not a string"))
+    }
+
+    fn add_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        self.run(&["flag", "add"], &[&id.to_string(), flag_name(flag)])
+            .map(|_| ())
+    }
+
+    fn remove_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        self.run(&["flag", "remove"], &[&id.to_string(), flag_name(flag)])
+            .map(|_| ())
+    }
+
+    fn move_to(&self, id: usize, folder: &str) -> io::Result<()> {
+        let id = id.to_string();
+        if self.version >= Some(Version::new(1, 0, 0)) {
+            self.run(&["message", "move"], &[folder, &id])
+        } else {
+            self.run(&["move"], &[&id, folder])
+        }
+        .map(|_| ())
+    }
+
+    /// Envelopes have no attachment field before 1.0.
+    fn lists_attachments(&self) -> bool {
+        self.version >= Some(Version::new(1, 0, 0))
+    }
+}
+
+/// Translate a query of the keywords of the IMAP search of himalaya 0.x, like
+/// `from alice or seen flagged`, to the filter of `envelope list` since 1.0,
+/// like `from alice and ( flag seen or flag flagged )`.
+///
+/// Words which are not keywords are searched in subjects, and `all`, `recent` and `header`
+/// criteria have no equivalent so they are dropped.
+fn filter_query(words: &[String]) -> Vec<String> {
+    let mut words = words.iter();
+    let mut filter = Vec::new();
+    while let Some(criterion) = criterion(&mut words) {
+        if criterion.is_empty() {
+            continue;
+        }
+        if !filter.is_empty() {
+            filter.push("and".to_string());
+        }
+        filter.extend(criterion);
+    }
+    filter
+}
+
+/// Translate the next criterion of the query, or get `None` at its end.
+fn criterion<'a, I: Iterator<Item = &'a String>>(words: &mut I) -> Option<Vec<String>> {
+    let word = words.next()?;
+    let lower = word.to_lowercase();
+    let mut pattern = |keyword: &str| {
+        let pattern = words.next().map_or_else(String::new, |w| quote(w));
+        vec![keyword.to_string(), pattern]
+    };
+    let criterion = match lower.as_str() {
+        "all" | "recent" => Vec::new(),
+        "header" => {
+            words.next();
+            words.next();
+            Vec::new()
+        }
+        "from" | "to" | "subject" | "body" | "before" => pattern(&lower),
+        "text" => pattern("body"),
+        "seen" | "answered" | "flagged" | "deleted" | "draft" => {
+            vec!["flag".to_string(), lower]
+        }
+        "new" | "unseen" | "unanswered" | "unflagged" | "undeleted" => {
+            let flag = if lower == "new" { "seen" } else { &lower[2..] };
+            vec!["not".to_string(), "flag".to_string(), flag.to_string()]
+        }
+        // criteria without equivalent are dropped with their operator
+        "not" => match criterion(words)? {
+            inner if inner.is_empty() => inner,
+            inner => [vec!["not".to_string()], inner].concat(),
+        },
+        "or" => match (criterion(words)?, criterion(words)?) {
+            (left, right) if left.is_empty() || right.is_empty() => Vec::new(),
+            (left, right) => [
+                vec!["(".to_string()],
+                left,
+                vec!["or".to_string()],
+                right,
+                vec![")".to_string()],
+            ]
+            .concat(),
+        },
+        _ => vec!["subject".to_string(), quote(word)],
+    };
+    Some(criterion)
+}
+
+/// Quote the pattern if it has spaces, to keep it a single word of the filter.
+fn quote(pattern: &str) -> String {
+    if pattern.contains(char::is_whitespace) {
+        format!("{:?}", pattern)
+    } else {
+        pattern.to_string()
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{flag_name, matches, parse_envelope, MailBackend};
+use crate::app::data::{Flag, Msg};
+
+/// Backend reading a Maildir account directly, as synchronized by mbsync or offlineimap.
+///
+/// Both layouts are supported: Maildir++ with `INBOX` at the root and `.Sub.Folder`
+/// directories, and folders in plain sub-directories. Ids are positions of the messages
+/// ordered by delivery, which stay the same until messages are added or removed.
+#[derive(Debug)]
+pub struct Maildir {
+    root: PathBuf,
+    folder: String,
+}
+
+/// Message file of the folder.
+struct Entry {
+    path: PathBuf,
+    /// whether it is still in `new`, not seen by any client yet
+    new: bool,
+}
+
+impl Maildir {
+    pub fn new(root: PathBuf, folder: &str) -> Self {
+        Maildir {
+            root,
+            folder: folder.to_string(),
+        }
+    }
+
+    /// Directory of a folder, the one with `cur`, `new` and `tmp`.
+    fn folder_dir(&self, folder: &str) -> PathBuf {
+        let verbatim = self.root.join(folder);
+        if is_maildir(&verbatim) {
+            verbatim
+        } else if folder.eq_ignore_ascii_case("INBOX") && is_maildir(&self.root) {
+            self.root.clone()
+        } else {
+            self.root.join(format!(".{}", folder.replace('/', ".")))
+        }
+    }
+
+    /// Messages of the folder, in order of delivery.
+    fn entries(&self) -> io::Result<Vec<Entry>> {
+        let dir = self.folder_dir(&self.folder);
+        if !is_maildir(&dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no maildir at {}", dir.display()),
+            ));
+        }
+
+        let mut entries = Vec::new();
+        for (sub, new) in [("cur", false), ("new", true)] {
+            for entry in fs::read_dir(dir.join(sub))? {
+                let path = entry?.path();
+                if path.is_file() {
+                    entries.push(Entry { path, new });
+                }
+            }
+        }
+        // names start with the time of delivery
+        entries.sort_by_cached_key(|entry| {
+            let name = file_name(&entry.path);
+            let time = name
+                .split('.')
+                .next()
+                .and_then(|time| time.parse::<u64>().ok())
+                .unwrap_or(u64::MAX);
+            (time, name)
+        });
+        Ok(entries)
+    }
+
+    fn entry(&self, id: usize) -> io::Result<Entry> {
+        id.checked_sub(1)
+            .and_then(|index| self.entries().ok()?.into_iter().nth(index))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no message {}", id)))
+    }
+
+    /// Rename the message file with the flags changed, moving it to `cur` if new.
+    fn set_flag(&self, id: usize, flag: &Flag, on: bool) -> io::Result<()> {
+        let letter = match flag {
+            Flag::Draft => 'D',
+            Flag::Flagged => 'F',
+            Flag::Answered => 'R',
+            Flag::Seen => 'S',
+            Flag::Deleted => 'T',
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("flag {} not supported by maildir", flag_name(flag)),
+                ))
+            }
+        };
+        let entry = self.entry(id)?;
+        let name = file_name(&entry.path);
+        let (base, info) = split_info(&name);
+        let mut letters: Vec<char> = info.chars().filter(|c| *c != letter).collect();
+        if on {
+            letters.push(letter);
+        }
+        letters.sort_unstable();
+
+        let dir = entry
+            .path
+            .parent()
+            .and_then(Path::parent)
+            .unwrap_or(&self.root);
+        let target = dir.join("cur").join(format!(
+            "{}:2,{}",
+            base,
+            letters.into_iter().collect::<String>()
+        ));
+        fs::rename(&entry.path, target)
+    }
+}
+
+fn is_maildir(dir: &Path) -> bool {
+    dir.join("cur").is_dir() && dir.join("new").is_dir()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Split a file name into its unique part and the letters of its flags.
+fn split_info(name: &str) -> (&str, &str) {
+    match name.rsplit_once(":2,") {
+        Some((base, info)) => (base, info),
+        None => (name, ""),
+    }
+}
+
+/// Build the envelope of a message from its headers and the flags of its file name.
+fn envelope(id: usize, entry: &Entry) -> io::Result<Msg> {
+    let mut flags: Vec<Flag> = split_info(&file_name(&entry.path))
+        .1
+        .chars()
+        .filter_map(|letter| match letter {
+            'D' => Some(Flag::Draft),
+            'F' => Some(Flag::Flagged),
+            'R' => Some(Flag::Answered),
+            'S' => Some(Flag::Seen),
+            'T' => Some(Flag::Deleted),
+            _ => None,
+        })
+        .collect();
+    if entry.new {
+        flags.push(Flag::Recent);
+    }
+    parse_envelope(id, &fs::read(&entry.path)?, flags)
+}
+
+impl MailBackend for Maildir {
+    fn folders(&self) -> io::Result<Vec<String>> {
+        let mut folders = Vec::new();
+        if is_maildir(&self.root) {
+            folders.push("INBOX".to_string());
+        }
+        let mut dirs = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let name = file_name(&path);
+                if !path.is_dir() || ["cur", "new", "tmp"].contains(&name.as_str()) {
+                    continue;
+                }
+                let folder = match name.strip_prefix('.') {
+                    // Maildir++ folders are all at the root
+                    Some(name) if prefix.is_empty() => name.replace('.', "/"),
+                    Some(_) => continue,
+                    None => format!("{}{}", prefix, name),
+                };
+                if is_maildir(&path) {
+                    folders.push(folder.clone());
+                }
+                if !name.starts_with('.') {
+                    dirs.push((path, format!("{}/", folder)));
+                }
+            }
+        }
+        folders.sort();
+        folders.dedup();
+        Ok(folders)
+    }
+
+    fn envelopes(&self) -> io::Result<Vec<Msg>> {
+        let mut envelopes = Vec::new();
+        for (index, entry) in self.entries()?.iter().enumerate() {
+            // skip unreadable messages instead of the whole folder
+            if let Ok(msg) = envelope(index + 1, entry) {
+                envelopes.push(msg);
+            }
+        }
+        envelopes.reverse();
+        Ok(envelopes)
+    }
+
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>> {
+        Ok(self
+            .envelopes()?
+            .into_iter()
+            .filter(|msg| matches(msg, query))
+            .collect())
+    }
+
+    fn read(&self, id: usize) -> io::Result<String> {
+        let content = fs::read(self.entry(id)?.path)?;
+        Ok(String::from_utf8_lossy(&content).to_string())
+    }
+
+    fn add_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        self.set_flag(id, flag, true)
+    }
+
+    fn remove_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        self.set_flag(id, flag, false)
+    }
+
+    fn move_to(&self, id: usize, folder: &str) -> io::Result<()> {
+        let target = self.folder_dir(folder);
+        if !is_maildir(&target) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no folder {}", folder),
+            ));
+        }
+        let entry = self.entry(id)?;
+        let sub = if entry.new { "new" } else { "cur" };
+        fs::rename(&entry.path, target.join(sub).join(file_name(&entry.path)))
+    }
+}
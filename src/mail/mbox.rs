@@ -0,0 +1,133 @@
+use std::{fs, io, path::PathBuf, sync::OnceLock};
+
+use mailparse::{parse_headers, MailHeaderMap};
+
+use super::{matches, parse_envelope, read_only, MailBackend};
+use crate::app::data::{Flag, Msg};
+
+/// Read-only backend showing the messages of an mbox file, like a mailing list archive.
+///
+/// The file is read once, ids are positions of the messages in it.
+#[derive(Debug)]
+pub struct Mbox {
+    path: PathBuf,
+    messages: OnceLock<Vec<Vec<u8>>>,
+}
+
+impl Mbox {
+    pub fn new(path: PathBuf) -> Self {
+        Mbox {
+            path,
+            messages: OnceLock::new(),
+        }
+    }
+
+    fn messages(&self) -> io::Result<&[Vec<u8>]> {
+        if let Some(messages) = self.messages.get() {
+            return Ok(messages);
+        }
+        let messages = split(&fs::read(&self.path)?);
+        Ok(self.messages.get_or_init(|| messages))
+    }
+}
+
+/// Split the content of an mbox into raw messages, without their `From ` lines.
+///
+/// Lines quoted as `>From ` by mboxrd and mboxo writers are unquoted once.
+pub fn split(content: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages: Vec<Vec<u8>> = Vec::new();
+    let mut blank = true;
+    for line in content.split_inclusive(|b| *b == b'\n') {
+        if blank && line.starts_with(b"From ") {
+            messages.push(Vec::new());
+            continue;
+        }
+        blank = line == b"\n" || line == b"\r\n";
+        let message = match messages.last_mut() {
+            Some(message) => message,
+            // garbage before the first message
+            None => continue,
+        };
+        let quotes = line.iter().take_while(|b| **b == b'>').count();
+        if quotes > 0 && line[quotes..].starts_with(b"From ") {
+            message.extend_from_slice(&line[1..]);
+        } else {
+            message.extend_from_slice(line);
+        }
+    }
+    // the blank line before the next `From ` belongs to the format
+    for message in messages.iter_mut() {
+        if message.ends_with(b"\r\n\r\n") {
+            message.truncate(message.len() - 2);
+        } else if message.ends_with(b"\n\n") {
+            message.pop();
+        }
+    }
+    messages
+}
+
+/// Flags kept by mail clients in the `Status` and `X-Status` headers.
+fn status_flags(content: &[u8]) -> Vec<Flag> {
+    let headers = match parse_headers(content) {
+        Ok((headers, _)) => headers,
+        Err(_) => return Vec::new(),
+    };
+    let status = headers.get_first_value("Status").unwrap_or_default()
+        + &headers.get_first_value("X-Status").unwrap_or_default();
+    let mut flags = Vec::new();
+    for (letter, flag) in [
+        ('R', Flag::Seen),
+        ('A', Flag::Answered),
+        ('F', Flag::Flagged),
+        ('D', Flag::Deleted),
+    ] {
+        if status.contains(letter) {
+            flags.push(flag);
+        }
+    }
+    flags
+}
+
+impl MailBackend for Mbox {
+    fn folders(&self) -> io::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn envelopes(&self) -> io::Result<Vec<Msg>> {
+        let mut envelopes = Vec::new();
+        for (index, content) in self.messages()?.iter().enumerate() {
+            if let Ok(msg) = parse_envelope(index + 1, content, status_flags(content)) {
+                envelopes.push(msg);
+            }
+        }
+        envelopes.reverse();
+        Ok(envelopes)
+    }
+
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>> {
+        Ok(self
+            .envelopes()?
+            .into_iter()
+            .filter(|msg| matches(msg, query))
+            .collect())
+    }
+
+    fn read(&self, id: usize) -> io::Result<String> {
+        id.checked_sub(1)
+            .and_then(|index| self.messages().ok()?.get(index))
+            .map(|content| String::from_utf8_lossy(content).to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no message {}", id)))
+    }
+
+    fn add_flag(&self, _id: usize, _flag: &Flag) -> io::Result<()> {
+        Err(read_only("mbox"))
+    }
+
+    fn remove_flag(&self, _id: usize, _flag: &Flag) -> io::Result<()> {
+        Err(read_only("mbox"))
+    }
+
+    fn move_to(&self, _id: usize, _folder: &str) -> io::Result<()> {
+        Err(read_only("mbox"))
+    }
+}
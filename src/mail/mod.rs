@@ -0,0 +1,206 @@
+//! Backends reading mail, through himalaya, directly on disk, from a notmuch index
+//! or from mbox and message files.
+
+mod eml;
+mod himalaya;
+mod maildir;
+mod mbox;
+mod notmuch;
+
+use std::{fmt::Debug, io, sync::Arc};
+
+use mailparse::{addrparse_header, dateparse, parse_headers, MailAddr, MailHeaderMap};
+use serde::Deserialize;
+
+pub use self::{
+    eml::Eml,
+    himalaya::Himalaya,
+    maildir::Maildir,
+    mbox::{split as split_mbox, Mbox},
+    notmuch::{Notmuch, QUERY_WORDS},
+};
+use crate::{
+    app::data::{Flag, Msg},
+    config::Config,
+    utils::format_timestamp,
+    version::Version,
+};
+
+/// Operations of the app on the folder of the configuration.
+///
+/// Ids are the ones of the envelopes listed by the backend.
+pub trait MailBackend: Debug + Send + Sync {
+    /// Names of the folders of the account.
+    fn folders(&self) -> io::Result<Vec<String>>;
+    /// Envelopes of the folder, newest first.
+    fn envelopes(&self) -> io::Result<Vec<Msg>>;
+    /// Envelopes matching the query, given as words of the command line.
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>>;
+    /// Raw source of the message.
+    fn read(&self, id: usize) -> io::Result<String>;
+    fn add_flag(&self, id: usize, flag: &Flag) -> io::Result<()>;
+    fn remove_flag(&self, id: usize, flag: &Flag) -> io::Result<()>;
+    /// Move the message to another folder.
+    fn move_to(&self, id: usize, folder: &str) -> io::Result<()>;
+    /// Whether envelopes tell if messages have attachments, for the column of the list.
+    fn lists_attachments(&self) -> bool {
+        true
+    }
+}
+
+/// Kind of backend, selected by the `backend` key of the configuration.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Himalaya,
+    /// Maildir at the path of the `maildir` key, synchronized by mbsync or offlineimap.
+    Maildir,
+    /// Index of notmuch, queried with the binary of the `notmuch` key.
+    Notmuch,
+    /// Mbox file at the path of the `mbox` key, read-only.
+    Mbox,
+    /// Single message file at the path of the `eml` key, read-only.
+    Eml,
+}
+
+impl BackendKind {
+    /// Name of the backend in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Himalaya => "himalaya",
+            BackendKind::Maildir => "maildir",
+            BackendKind::Notmuch => "notmuch",
+            BackendKind::Mbox => "mbox",
+            BackendKind::Eml => "eml",
+        }
+    }
+
+    /// Whether the backend reads a single file, always at hand and known only by its path.
+    pub fn reads_file(&self) -> bool {
+        matches!(self, BackendKind::Mbox | BackendKind::Eml)
+    }
+}
+
+/// Open the backend of the configuration on its folder.
+pub fn open(config: &Config, version: Option<Version>) -> Arc<dyn MailBackend> {
+    match config.backend {
+        BackendKind::Himalaya => Arc::new(Himalaya::new(config.clone(), version)),
+        BackendKind::Maildir => Arc::new(Maildir::new(
+            config.maildir.clone().unwrap_or_default(),
+            config.folder(),
+        )),
+        BackendKind::Notmuch => Arc::new(Notmuch::new(config.notmuch.clone(), config.folder())),
+        BackendKind::Mbox => Arc::new(Mbox::new(config.mbox.clone().unwrap_or_default())),
+        BackendKind::Eml => Arc::new(Eml::new(config.eml.clone().unwrap_or_default())),
+    }
+}
+
+/// Name of the flag in queries and commands.
+pub fn flag_name(flag: &Flag) -> &str {
+    match flag {
+        Flag::Seen => "seen",
+        Flag::Answered => "answered",
+        Flag::Flagged => "flagged",
+        Flag::Deleted => "deleted",
+        Flag::Draft => "draft",
+        Flag::Recent => "recent",
+        Flag::MayCreate => "maycreate",
+        Flag::Custom(name) => name,
+    }
+}
+
+fn read_only(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("{} is read-only", what),
+    )
+}
+
+/// Build the envelope of a raw message from its headers.
+fn parse_envelope(id: usize, content: &[u8], flags: Vec<Flag>) -> io::Result<Msg> {
+    let (headers, _) =
+        parse_headers(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let sender = headers
+        .get_first_header("From")
+        .and_then(|from| addrparse_header(from).ok())
+        .and_then(|addrs| match addrs.first()? {
+            MailAddr::Single(info) => Some(info.display_name.clone().unwrap_or(info.addr.clone())),
+            MailAddr::Group(group) => Some(group.group_name.clone()),
+        })
+        .unwrap_or_default();
+    let date = headers
+        .get_first_value("Date")
+        .map(|date| match dateparse(&date) {
+            Ok(timestamp) => format_timestamp(timestamp),
+            Err(_) => date,
+        })
+        .unwrap_or_default();
+    let has_attachment = headers
+        .get_first_value("Content-Type")
+        .is_some_and(|kind| kind.to_lowercase().starts_with("multipart/mixed"));
+
+    Ok(Msg {
+        id,
+        flags,
+        subject: headers.get_first_value("Subject").unwrap_or_default(),
+        sender,
+        date,
+        has_attachment,
+    })
+}
+
+/// Words of the query, with the words of `"quoted phrases"` joined.
+fn phrases(query: &[String]) -> Vec<String> {
+    query
+        .join(" ")
+        .split('"')
+        .enumerate()
+        .flat_map(|(i, part)| match i % 2 {
+            // between quotes
+            1 => vec![part.to_string()],
+            _ => part
+                .split_whitespace()
+                .map(|word| word.to_string())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Check whether an envelope matches a query like `from "Alice Smith" unseen report`,
+/// using the keywords of himalaya searches.
+fn matches(msg: &Msg, query: &[String]) -> bool {
+    let contains = |text: &str, word: &str| text.to_lowercase().contains(&word.to_lowercase());
+    let query = phrases(query);
+    let mut words = query.iter();
+    let mut negate = false;
+    while let Some(word) = words.next() {
+        let matched = match word.to_lowercase().as_str() {
+            "not" => {
+                negate = !negate;
+                continue;
+            }
+            "all" => true,
+            "from" => words.next().is_some_and(|w| contains(&msg.sender, w)),
+            "subject" => words.next().is_some_and(|w| contains(&msg.subject, w)),
+            "new" => !msg.flags.contains(&Flag::Seen),
+            "seen" => msg.flags.contains(&Flag::Seen),
+            "answered" => msg.flags.contains(&Flag::Answered),
+            "flagged" => msg.flags.contains(&Flag::Flagged),
+            "deleted" => msg.flags.contains(&Flag::Deleted),
+            "draft" => msg.flags.contains(&Flag::Draft),
+            "recent" => msg.flags.contains(&Flag::Recent),
+            "unseen" => !msg.flags.contains(&Flag::Seen),
+            "unanswered" => !msg.flags.contains(&Flag::Answered),
+            "unflagged" => !msg.flags.contains(&Flag::Flagged),
+            "undeleted" => !msg.flags.contains(&Flag::Deleted),
+            _ => contains(&msg.subject, word) || contains(&msg.sender, word),
+        };
+        if matched == negate {
+            return false;
+        }
+        negate = false;
+    }
+    true
+}
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, io, path::PathBuf, process::Command, sync::Mutex};
+
+use mailparse::{addrparse, MailAddr};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{flag_name, MailBackend};
+use crate::{
+    app::data::{Flag, Msg},
+    utils::{format_timestamp, output},
+};
+
+/// Words of notmuch queries, completed in searches.
+pub const QUERY_WORDS: [&str; 12] = [
+    "and",
+    "attachment:",
+    "date:",
+    "folder:",
+    "from:",
+    "id:",
+    "not",
+    "or",
+    "subject:",
+    "tag:",
+    "thread:",
+    "to:",
+];
+
+/// Tags shown as flags of envelopes, or by the attachment column.
+const FLAG_TAGS: [&str; 6] = [
+    "unread",
+    "attachment",
+    "replied",
+    "flagged",
+    "draft",
+    "deleted",
+];
+
+/// Backend running queries on a notmuch index.
+///
+/// Folders are tags, `INBOX` being `tag:inbox`, or raw queries when they contain a `:`.
+/// Messages get ids in the order they are first listed, kept for the whole session.
+#[derive(Debug)]
+pub struct Notmuch {
+    program: PathBuf,
+    folder: String,
+    /// notmuch ids of the messages, the id of a message is its position from 1
+    ids: Mutex<Vec<String>>,
+}
+
+/// Message of `notmuch show --format=json`.
+#[derive(Deserialize)]
+struct Message {
+    id: String,
+    #[serde(default = "matched")]
+    r#match: bool,
+    timestamp: i64,
+    tags: Vec<String>,
+    headers: HashMap<String, String>,
+}
+
+fn matched() -> bool {
+    true
+}
+
+impl Notmuch {
+    pub fn new(program: PathBuf, folder: &str) -> Self {
+        Notmuch {
+            program,
+            folder: folder.to_string(),
+            ids: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<String> {
+        output(Command::new(&self.program).args(args))
+    }
+
+    /// Id of the message in the app, given one if it is new.
+    fn id(&self, message_id: &str) -> usize {
+        let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        match ids.iter().position(|id| id == message_id) {
+            Some(index) => index + 1,
+            None => {
+                ids.push(message_id.to_string());
+                ids.len()
+            }
+        }
+    }
+
+    /// Query matching the message with the given id in the app.
+    fn message_query(&self, id: usize) -> io::Result<String> {
+        let ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        id.checked_sub(1)
+            .and_then(|index| ids.get(index))
+            .map(|message_id| format!("id:\"{}\"", message_id.replace('"', "\"\"")))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no message {}", id)))
+    }
+
+    /// Envelopes of the messages matching the query, newest first.
+    fn show(&self, query: &str) -> io::Result<Vec<Msg>> {
+        let output = self.run(&[
+            "show",
+            "--format=json",
+            "--body=false",
+            "--entire-thread=false",
+            query,
+        ])?;
+        let mut messages = Vec::new();
+        collect(serde_json::from_str(&output)?, &mut messages)?;
+        messages.sort_by_key(|message| -message.timestamp);
+        Ok(messages
+            .into_iter()
+            .map(|message| self.envelope(message))
+            .collect())
+    }
+
+    fn envelope(&self, message: Message) -> Msg {
+        let mut flags = Vec::new();
+        if !message.tags.iter().any(|tag| tag == "unread") {
+            flags.push(Flag::Seen);
+        }
+        for tag in &message.tags {
+            match tag.as_str() {
+                "unread" | "attachment" => {}
+                "replied" => flags.push(Flag::Answered),
+                "flagged" => flags.push(Flag::Flagged),
+                "draft" => flags.push(Flag::Draft),
+                "deleted" => flags.push(Flag::Deleted),
+                _ => flags.push(Flag::Custom(tag.clone())),
+            }
+        }
+
+        let header = |name: &str| message.headers.get(name).cloned().unwrap_or_default();
+        let from = header("From");
+        let sender = match addrparse(&from)
+            .ok()
+            .as_ref()
+            .and_then(|addrs| addrs.first())
+        {
+            Some(MailAddr::Single(info)) => info.display_name.clone().unwrap_or(info.addr.clone()),
+            Some(MailAddr::Group(group)) => group.group_name.clone(),
+            None => from,
+        };
+
+        Msg {
+            id: self.id(&message.id),
+            flags,
+            subject: header("Subject"),
+            sender,
+            date: format_timestamp(message.timestamp),
+            has_attachment: message.tags.iter().any(|tag| tag == "attachment"),
+        }
+    }
+
+    /// Change tags of a message, like `+work` or `-inbox`.
+    fn tag(&self, id: usize, changes: &[String]) -> io::Result<()> {
+        let query = self.message_query(id)?;
+        let mut args = vec!["tag"];
+        args.extend(changes.iter().map(|change| change.as_str()));
+        args.extend(["--", &query]);
+        self.run(&args).map(|_| ())
+    }
+}
+
+/// Query of a folder, which is a tag unless it is a query already.
+fn folder_query(folder: &str) -> String {
+    if folder.eq_ignore_ascii_case("INBOX") {
+        "tag:inbox".to_string()
+    } else if folder.contains(':') {
+        folder.to_string()
+    } else {
+        format!("tag:{}", folder)
+    }
+}
+
+/// Gather the messages of the threads, whatever their nesting.
+fn collect(value: Value, messages: &mut Vec<Message>) -> io::Result<()> {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                collect(value, messages)?;
+            }
+        }
+        Value::Object(ref object) if object.contains_key("id") => {
+            let message: Message = serde_json::from_value(value)?;
+            // other messages of the thread are there too when asked
+            if message.r#match {
+                messages.push(message);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Tag standing for the flag, and whether it is set when the flag is.
+fn flag_tag(flag: &Flag) -> io::Result<(&str, bool)> {
+    match flag {
+        Flag::Seen => Ok(("unread", false)),
+        Flag::Answered => Ok(("replied", true)),
+        Flag::Flagged => Ok(("flagged", true)),
+        Flag::Draft => Ok(("draft", true)),
+        Flag::Deleted => Ok(("deleted", true)),
+        Flag::Custom(tag) => Ok((tag, true)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("flag {} not supported by notmuch", flag_name(flag)),
+        )),
+    }
+}
+
+impl MailBackend for Notmuch {
+    fn folders(&self) -> io::Result<Vec<String>> {
+        let output = self.run(&["search", "--format=json", "--output=tags", "*"])?;
+        let tags: Vec<String> = serde_json::from_str(&output)?;
+        Ok(tags
+            .into_iter()
+            .filter(|tag| !FLAG_TAGS.contains(&tag.as_str()))
+            .collect())
+    }
+
+    fn envelopes(&self) -> io::Result<Vec<Msg>> {
+        self.show(&folder_query(&self.folder))
+    }
+
+    /// Search all messages with a raw notmuch query, like `from:alice and tag:work`.
+    fn search(&self, query: &[String]) -> io::Result<Vec<Msg>> {
+        self.show(&query.join(" "))
+    }
+
+    fn read(&self, id: usize) -> io::Result<String> {
+        self.run(&["show", "--format=raw", &self.message_query(id)?])
+    }
+
+    fn add_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        let (tag, set) = flag_tag(flag)?;
+        self.tag(id, &[format!("{}{}", if set { '+' } else { '-' }, tag)])
+    }
+
+    fn remove_flag(&self, id: usize, flag: &Flag) -> io::Result<()> {
+        let (tag, set) = flag_tag(flag)?;
+        self.tag(id, &[format!("{}{}", if set { '-' } else { '+' }, tag)])
+    }
+
+    /// Retag the message, removing the tag of the current folder.
+    fn move_to(&self, id: usize, folder: &str) -> io::Result<()> {
+        let target = folder_query(folder);
+        let tag = target.strip_prefix("tag:").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a tag", folder),
+            )
+        })?;
+        let mut changes = Vec::new();
+        if let Some(current) = folder_query(&self.folder).strip_prefix("tag:") {
+            changes.push(format!("-{}", current));
+        }
+        changes.push(format!("+{}", tag));
+        self.tag(id, &changes)
+    }
+}
@@ -1,25 +1,90 @@
-mod app;
-mod backend;
-mod filter;
-mod keymap;
-mod ui;
-mod utils;
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use std::{error::Error, time::Duration};
-
-use backend::run;
 use clap::Parser;
+use himalaya_tui::{backend::run, config::Config, mail::BackendKind, utils::private_temp_dir};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
     #[clap(short, long, default_value_t = 250)]
     tick_rate: u64,
+    /// Path to the configuration file
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+    /// Directory where attachments are saved
+    #[clap(long)]
+    downloads_dir: Option<PathBuf>,
+    /// Path of the himalaya binary
+    #[clap(long)]
+    himalaya: Option<PathBuf>,
+    /// Global flag given to himalaya, can be repeated
+    #[clap(long = "himalaya-arg", allow_hyphen_values = true)]
+    himalaya_args: Vec<String>,
+    /// Seconds between background refreshes of the folder, 0 to disable
+    #[clap(short, long)]
+    poll_interval: Option<u64>,
+    /// Watch the folder for changes instead of polling
+    #[clap(long)]
+    idle: bool,
+    /// Open an mbox file read-only instead of the account
+    #[clap(long)]
+    mbox: Option<PathBuf>,
+    /// Open a single message file in the reader, `-` to read it from stdin
+    #[clap(long, conflicts_with = "mbox")]
+    eml: Option<PathBuf>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
     let tick_rate = Duration::from_millis(args.tick_rate);
-    run(tick_rate)?;
-    Ok(())
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(dir) = args.downloads_dir {
+        config.downloads_dir = dir;
+    }
+    if let Some(himalaya) = args.himalaya {
+        config.himalaya = himalaya;
+    }
+    config.himalaya_args.extend(args.himalaya_args);
+    if let Some(interval) = args.poll_interval {
+        config.poll_interval = interval;
+    }
+    config.idle |= args.idle;
+    if let Some(mbox) = args.mbox {
+        // the file does not change while it is read
+        config.backend = BackendKind::Mbox;
+        config.mbox = Some(mbox);
+        config.idle = false;
+        config.poll_interval = 0;
+    }
+    // keep the message of stdin in a file until the end
+    let mut stdin_file = None;
+    if let Some(mut eml) = args.eml {
+        if eml == Path::new("-") {
+            let mut raw = Vec::new();
+            io::stdin().read_to_end(&mut raw)?;
+            let dir = private_temp_dir("himalaya-tui")?;
+            stdin_file = Some(dir.clone());
+            eml = dir.join("stdin.eml");
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options.open(&eml)?.write_all(&raw)?;
+        }
+        config.backend = BackendKind::Eml;
+        config.eml = Some(eml);
+        config.idle = false;
+        config.poll_interval = 0;
+    }
+    let result = run(tick_rate, config);
+    if let Some(dir) = stdin_file {
+        let _ = fs::remove_dir_all(dir);
+    }
+    result
 }
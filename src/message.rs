@@ -0,0 +1,186 @@
+use core::fmt;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use mailparse::{DispositionType, MailParseError, ParsedMail};
+
+use crate::utils::{human_size, unique_path};
+
+/// Headers shown in the reader when full headers are hidden.
+pub const SUMMARY_HEADERS: [&str; 5] = ["From", "To", "Cc", "Date", "Subject"];
+
+/// A MIME message parsed from the raw output of himalaya.
+#[derive(Debug, Default)]
+pub struct Message {
+    pub headers: Vec<(String, String)>,
+    pub parts: Vec<Part>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Message {
+    /// Parse a raw RFC 822 message.
+    pub fn parse(raw: &[u8]) -> Result<Self, MailParseError> {
+        let mail = mailparse::parse_mail(raw)?;
+        let headers = mail
+            .headers
+            .iter()
+            .map(|h| (h.get_key(), h.get_value()))
+            .collect();
+
+        let mut message = Message {
+            headers,
+            parts: Vec::new(),
+            attachments: Vec::new(),
+        };
+        message.collect_parts(&mail)?;
+        Ok(message)
+    }
+
+    /// Walk the MIME tree, keeping inline text parts and attachments.
+    fn collect_parts(&mut self, mail: &ParsedMail) -> Result<(), MailParseError> {
+        if mail.ctype.mimetype.starts_with("multipart/") {
+            for part in &mail.subparts {
+                self.collect_parts(part)?;
+            }
+            return Ok(());
+        }
+
+        let disposition = mail.get_content_disposition();
+        let kind = PartKind::from_mime(&mail.ctype.mimetype);
+        match kind {
+            Some(kind) if disposition.disposition != DispositionType::Attachment => {
+                self.parts.push(Part {
+                    kind,
+                    body: mail.get_body()?,
+                });
+            }
+            _ => {
+                let filename = disposition
+                    .params
+                    .get("filename")
+                    .or_else(|| mail.ctype.params.get("name"))
+                    .cloned();
+                self.attachments.push(Attachment {
+                    filename,
+                    mime_type: mail.ctype.mimetype.clone(),
+                    content: mail.get_body_raw()?,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the first value of a header, ignoring case of the name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Headers to display in the reader, either all of them or the summary only.
+    pub fn display_headers(&self, full: bool) -> Vec<(&str, &str)> {
+        if full {
+            self.headers
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect()
+        } else {
+            SUMMARY_HEADERS
+                .iter()
+                .filter_map(|key| self.header(key).map(|value| (*key, value)))
+                .collect()
+        }
+    }
+
+    /// Get the body part of the given kind, falling back to any other text part.
+    pub fn body(&self, kind: &PartKind) -> Option<&Part> {
+        self.parts
+            .iter()
+            .find(|p| &p.kind == kind)
+            .or_else(|| self.parts.first())
+    }
+
+    pub fn has_part(&self, kind: &PartKind) -> bool {
+        self.parts.iter().any(|p| &p.kind == kind)
+    }
+}
+
+/// Non-text or attached part of a message.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: Option<String>,
+    pub mime_type: String,
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    /// Name of the attachment, stripped from any directory component.
+    pub fn name(&self) -> String {
+        self.filename
+            .as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "attachment".to_string())
+    }
+
+    pub fn size(&self) -> String {
+        human_size(self.content.len())
+    }
+
+    /// Save the attachment into the directory without overwriting existing files,
+    /// and return the path of the written file.
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = unique_path(dir, &self.name());
+        fs::write(&path, &self.content)?;
+        Ok(path)
+    }
+}
+
+/// Inline text part of a message.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub kind: PartKind,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum PartKind {
+    #[default]
+    Plain,
+    Html,
+}
+
+impl PartKind {
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "text/plain" => Some(PartKind::Plain),
+            "text/html" => Some(PartKind::Html),
+            _ => None,
+        }
+    }
+
+    /// Get the other kind of text part.
+    pub fn toggle(&self) -> Self {
+        match self {
+            PartKind::Plain => PartKind::Html,
+            PartKind::Html => PartKind::Plain,
+        }
+    }
+}
+
+impl fmt::Display for PartKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                PartKind::Plain => "text/plain",
+                PartKind::Html => "text/html",
+            }
+        )
+    }
+}
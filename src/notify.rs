@@ -0,0 +1,151 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::Deserialize;
+
+use crate::app::data::Msg;
+
+/// Number of messages the command is run for, the others are summarized in one more run.
+pub const MAX_COMMANDS: usize = 3;
+
+/// Notifications of new messages, read from the `[notify]` table of the configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotifyConfig {
+    /// Command run for each new message, with `HIMALAYA_SENDER`, `HIMALAYA_SUBJECT`,
+    /// `HIMALAYA_DATE`, `HIMALAYA_ID` and `HIMALAYA_FOLDER` in its environment.
+    ///
+    /// Past `MAX_COMMANDS` messages, it is run once more for the rest with their senders
+    /// joined and their number in `HIMALAYA_COUNT`.
+    pub command: Vec<String>,
+    /// Notification emitted by the terminal itself.
+    pub terminal: TerminalNotification,
+    /// Folders to notify for, all of them if empty.
+    pub folders: Vec<String>,
+    /// Only notify for senders containing one of these, ignoring case.
+    pub senders: Vec<String>,
+    /// Only notify for subjects containing one of these, ignoring case.
+    pub subjects: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalNotification {
+    #[default]
+    None,
+    /// Ring the terminal bell.
+    Bell,
+    /// Desktop notification of iTerm2, kitty, WezTerm and others.
+    Osc9,
+    /// Desktop notification of urxvt, foot, Ghostty and VTE based terminals.
+    Osc777,
+}
+
+impl NotifyConfig {
+    /// Check whether the rules allow to notify for the message.
+    pub fn matches(&self, folder: &str, msg: &Msg) -> bool {
+        let contains_any = |patterns: &[String], text: &str| {
+            let text = text.to_lowercase();
+            patterns.is_empty() || patterns.iter().any(|p| text.contains(&p.to_lowercase()))
+        };
+
+        (self.folders.is_empty() || self.folders.iter().any(|f| f.eq_ignore_ascii_case(folder)))
+            && contains_any(&self.senders, &msg.sender)
+            && contains_any(&self.subjects, &msg.subject)
+    }
+
+    /// Notify for the new messages allowed by the rules.
+    pub fn notify(&self, folder: &str, msgs: &[&Msg]) -> io::Result<()> {
+        let msgs: Vec<&Msg> = msgs
+            .iter()
+            .copied()
+            .filter(|msg| self.matches(folder, msg))
+            .collect();
+        if msgs.is_empty() {
+            return Ok(());
+        }
+
+        if let Some((program, args)) = self.command.split_first() {
+            let run = |env: &[(&str, String)]| -> io::Result<()> {
+                let mut child = Command::new(program)
+                    .args(args)
+                    .envs(env.iter().map(|(key, value)| (key, value)))
+                    .env("HIMALAYA_FOLDER", folder)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                // reap the process without blocking the interface
+                thread::spawn(move || child.wait());
+                Ok(())
+            };
+            // a full folder arriving at once must not start a process per message
+            let split = if msgs.len() > MAX_COMMANDS {
+                MAX_COMMANDS - 1
+            } else {
+                msgs.len()
+            };
+            for msg in &msgs[..split] {
+                run(&[
+                    ("HIMALAYA_SENDER", msg.sender.clone()),
+                    ("HIMALAYA_SUBJECT", msg.subject.clone()),
+                    ("HIMALAYA_DATE", msg.date.clone()),
+                    ("HIMALAYA_ID", msg.id.to_string()),
+                    ("HIMALAYA_COUNT", "1".to_string()),
+                ])?;
+            }
+            let rest = &msgs[split..];
+            if !rest.is_empty() {
+                run(&[
+                    ("HIMALAYA_SENDER", senders(rest)),
+                    ("HIMALAYA_SUBJECT", format!("{} more new mails", rest.len())),
+                    ("HIMALAYA_COUNT", rest.len().to_string()),
+                ])?;
+            }
+        }
+
+        let title = match msgs.len() {
+            1 => format!("New mail from {}", msgs[0].sender),
+            n => format!("{} new mails in {}", n, folder),
+        };
+        let body = match msgs.len() {
+            1 => msgs[0].subject.clone(),
+            _ => senders(&msgs),
+        };
+        let sequence = match self.terminal {
+            TerminalNotification::None => return Ok(()),
+            TerminalNotification::Bell => "\x07".to_string(),
+            TerminalNotification::Osc9 => {
+                format!("\x1b]9;{}: {}\x07", sanitize(&title), sanitize(&body))
+            }
+            TerminalNotification::Osc777 => format!(
+                "\x1b]777;notify;{};{}\x07",
+                sanitize(&title).replace(';', ","),
+                sanitize(&body).replace(';', ",")
+            ),
+        };
+
+        let mut stdout = io::stdout();
+        stdout.write_all(sequence.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Distinct senders of the messages, in order.
+fn senders(msgs: &[&Msg]) -> String {
+    let mut senders: Vec<&str> = Vec::new();
+    for msg in msgs {
+        if !senders.contains(&msg.sender.as_str()) {
+            senders.push(&msg.sender);
+        }
+    }
+    senders.join(", ")
+}
+
+/// Remove control chars which would end the escape sequence early.
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
@@ -0,0 +1,115 @@
+use crate::{
+    filter::COMMANDS,
+    keymap::{Event, KeyMode, Keymap},
+    utils::fuzzy_score,
+};
+
+/// Action run from the command palette.
+#[derive(Debug, Clone)]
+pub enum Action {
+    Event(Event),
+    /// Command of the command line, which is opened with it.
+    Command(&'static str),
+}
+
+#[derive(Debug)]
+pub struct PaletteEntry {
+    /// keybindings running the action, if any
+    pub keys: String,
+    pub name: String,
+    pub description: &'static str,
+    pub action: Action,
+}
+
+/// Fuzzy searchable list of the actions available in a mode.
+#[derive(Debug)]
+pub struct Palette {
+    pub filter: String,
+    entries: Vec<PaletteEntry>,
+    pub selected: usize,
+}
+
+impl Palette {
+    /// List the events bound in the mode, then the commands of the command line.
+    pub fn new(keymap: &Keymap, mode: &KeyMode) -> Self {
+        let mut entries: Vec<PaletteEntry> = Vec::new();
+        for keybind in keymap.keybinds(mode) {
+            let event = keybind.event();
+            if *event == Event::OpenPalette {
+                continue;
+            }
+            match entries
+                .iter_mut()
+                .find(|entry| matches!(&entry.action, Action::Event(e) if e == event))
+            {
+                Some(entry) => entry.keys = format!("{}, {}", entry.keys, keybind),
+                None => entries.push(PaletteEntry {
+                    keys: keybind.to_string(),
+                    name: format!("{:?}", event),
+                    description: event.description(),
+                    action: Action::Event(event.clone()),
+                }),
+            }
+        }
+        entries.extend(COMMANDS.iter().map(|(name, description)| PaletteEntry {
+            keys: String::new(),
+            name: format!(":{}", name),
+            description,
+            action: Action::Command(name),
+        }));
+
+        Palette {
+            filter: String::new(),
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Entries matching the filter on their name or description, best matches first.
+    pub fn matches(&self) -> Vec<&PaletteEntry> {
+        let mut matches: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let score = fuzzy_score(&self.filter, &entry.name)
+                    .max(fuzzy_score(&self.filter, entry.description));
+                score.map(|score| (score, entry))
+            })
+            .collect();
+        if !self.filter.is_empty() {
+            matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        matches.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn next(&mut self) {
+        let size = self.matches().len();
+        if size != 0 {
+            self.selected = (self.selected + 1) % size;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        let size = self.matches().len();
+        if size != 0 {
+            self.selected = (self.selected + size - 1) % size;
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.filter.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.filter.pop();
+        self.selected = 0;
+    }
+
+    /// Action of the selected entry.
+    pub fn action(&self) -> Option<Action> {
+        self.matches()
+            .get(self.selected)
+            .map(|entry| entry.action.clone())
+    }
+}
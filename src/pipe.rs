@@ -0,0 +1,105 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Options of the `:pipe` command, like `pipe -d -i urlview`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipe {
+    /// Send the decoded text instead of the raw message.
+    pub decoded: bool,
+    /// Give the terminal to the command, for programs with their own interface.
+    pub interactive: bool,
+    pub command: String,
+}
+
+impl Pipe {
+    /// Read the options before the shell command, `-d` for decoded and `-i` for interactive.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let mut pipe = Pipe {
+            decoded: false,
+            interactive: false,
+            command: String::new(),
+        };
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.peek() {
+            match arg.as_str() {
+                "-d" => pipe.decoded = true,
+                "-i" => pipe.interactive = true,
+                "-di" | "-id" => {
+                    pipe.decoded = true;
+                    pipe.interactive = true;
+                }
+                _ => break,
+            }
+            args.next();
+        }
+        pipe.command = args.cloned().collect::<Vec<_>>().join(" ");
+        Some(pipe).filter(|pipe| !pipe.command.trim().is_empty())
+    }
+
+    fn shell(&self) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.command);
+        command
+    }
+
+    /// Run the command with the input on its stdin, and get its output and error output.
+    pub fn run(&self, input: &str) -> io::Result<(bool, String)> {
+        let mut child = self
+            .shell()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // write from another thread so a command printing before reading does not block
+        let mut stdin = child.stdin.take();
+        let input = input.to_string();
+        let writer = thread::spawn(move || {
+            if let Some(stdin) = stdin.as_mut() {
+                // the command may not read everything
+                let _ = stdin.write_all(input.as_bytes());
+            }
+        });
+        let output = child.wait_with_output()?;
+        let _ = writer.join();
+
+        let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok((output.status.success(), text))
+    }
+
+    /// Command reading the input from a file of the private directory, to run with the
+    /// terminal restored.
+    ///
+    /// The file is only readable by the user, and removed before the command runs: it reads
+    /// the input from the descriptor it inherits.
+    pub fn interactive(&self, input: &str, dir: &Path) -> io::Result<Command> {
+        let path = dir.join("pipe.eml");
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        let file = file
+            .write_all(input.as_bytes())
+            .and_then(|_| File::open(&path));
+        let _ = fs::remove_file(&path);
+
+        let mut command = self.shell();
+        command.stdin(file?);
+        Ok(command)
+    }
+}
+
+/// Output of a piped command, shown in a popup.
+#[derive(Debug)]
+pub struct PipeOutput {
+    pub command: String,
+    pub success: bool,
+    pub text: String,
+    pub scroll: u16,
+}
@@ -1,30 +1,78 @@
+use std::time::Duration;
+
 use linkify::LinkFinder;
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+    widgets::{
+        Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+    },
     Frame,
 };
 
 use crate::{
-    app::{App, AppState},
+    app::{
+        data::{Flag, RenderedHtml, ReviewFlags},
+        App, AppState,
+    },
+    compose::Draft,
     filter::Filter,
+    html,
     keymap::KeyMode,
+    message::PartKind,
 };
 
 /// Draw UI
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let chunks = Layout::default()
-        .constraints([Constraint::Min(18), Constraint::Length(3)].as_ref())
+        .constraints(
+            [
+                Constraint::Min(18),
+                Constraint::Length(1),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
         .split(f.size());
-    if app.state.content.0.is_empty() {
+    if app.draft.is_some() {
+        draw_compose(f, app, chunks[0]);
+    } else if app.state.message.is_none() {
         draw_msg_list(f, app, chunks[0]);
     } else {
         draw_content(f, app, chunks[0]);
     }
-    draw_commands(f, app, chunks[1]);
+    draw_status(f, app, chunks[1]);
+    draw_commands(f, app, chunks[2]);
+
+    if app.picker.is_some() {
+        draw_picker(f, app, centered_rect(70, 70, chunks[0]));
+    }
+    if app.palette.is_some() {
+        draw_palette(f, app, centered_rect(70, 70, chunks[0]));
+    }
+    if app.link_picker.is_some() {
+        draw_link_picker(f, app, centered_rect(70, 50, chunks[0]));
+    }
+    if app.pipe_output.is_some() {
+        draw_pipe_output(f, app, centered_rect(80, 70, chunks[0]));
+    }
+    if app.help.is_some() {
+        draw_help(f, app, centered_rect(70, 70, chunks[0]));
+    }
+}
+
+/// Get a rectangle centered in the area, sized in percentage of it.
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
 }
 
 /// Draw mails list
@@ -42,32 +90,48 @@ pub fn draw_msg_list<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
         .add_modifier(Modifier::BOLD);
     let header_style = Style::default().bg(Color::Blue);
 
-    let header_cells = ["ID", "FLAGS", "SUBJECT", "SENDER", "DATE"]
+    // the attachment column is left out when the backend cannot tell
+    let attachments = app.mail.lists_attachments();
+    let header_cells = ["ID", "FLAGS", "", "SUBJECT", "SENDER", "DATE"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+        .enumerate()
+        .filter(|(i, _)| attachments || *i != 2)
+        .map(|(_, h)| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells)
         .style(header_style)
         .height(1)
         .bottom_margin(1);
-    let rows: Vec<Vec<String>> = app
+    let rows: Vec<(Vec<String>, bool)> = app
         .emails
         .iter()
         .map(|m| {
-            let flags = m.flags_string();
-            vec![
-                m.id.to_string(),
-                flags,
-                m.subject.clone(),
-                m.sender.clone(),
-                m.date.clone(),
-            ]
+            let mut cells = vec![m.id.to_string(), m.flags_string()];
+            if attachments {
+                cells.push(if m.has_attachment { "📎" } else { "" }.to_string());
+            }
+            cells.extend([m.subject.clone(), m.sender.clone(), m.date.clone()]);
+            (cells, app.marked.iter().any(|marked| marked.same(m)))
         })
         .collect();
 
-    let rows = rows.iter().map(|m| {
+    let rows = rows.iter().map(|(m, marked)| {
         let cells = m.iter().map(|m| m.as_str());
-        Row::new(cells).height(1).bottom_margin(1)
+        let style = if *marked {
+            Style::default().fg(Color::Magenta)
+        } else {
+            Style::default()
+        };
+        Row::new(cells).style(style).height(1).bottom_margin(1)
     });
+    let mut widths = vec![Constraint::Length(2), Constraint::Length(5)];
+    if attachments {
+        widths.push(Constraint::Length(2));
+    }
+    widths.extend([
+        Constraint::Percentage(50),
+        Constraint::Percentage(20),
+        Constraint::Length(10),
+    ]);
     let t = Table::new(rows)
         .header(header)
         .block(
@@ -78,16 +142,108 @@ pub fn draw_msg_list<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
         .highlight_style(selected_style)
         .highlight_symbol(">")
         .column_spacing(2)
-        .widths(&[
-            Constraint::Length(2),
-            Constraint::Length(5),
-            Constraint::Percentage(50),
-            Constraint::Percentage(20),
-            Constraint::Length(10),
-        ]);
+        .widths(&widths);
     f.render_stateful_widget(t, area, &mut app.state.msg_table);
 }
 
+/// Draw status line, with the folder and its counts on the left, and sync state on the right.
+pub fn draw_status<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let total = app.emails.len();
+    let unread = app
+        .emails
+        .iter()
+        .filter(|m| !m.flags.contains(&Flag::Seen))
+        .count();
+    let flagged = app
+        .emails
+        .iter()
+        .filter(|m| m.flags.contains(&Flag::Flagged))
+        .count();
+
+    let mut left = vec![
+        Span::styled(
+            format!(" {}/{} ", app.config.account(), app.config.folder()),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" {} messages", total)),
+        Span::styled(" · ", dim),
+        Span::raw(format!("{} unread", unread)),
+        Span::styled(" · ", dim),
+        Span::raw(format!("{} flagged", flagged)),
+    ];
+    if let Some(Filter(filter)) = app.curr_filter() {
+        left.push(Span::styled(" · ", dim));
+        left.push(Span::styled(
+            format!("filter: {}", filter),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    let mut right = Vec::new();
+    if app.new_mail > 0 {
+        right.push(Span::styled(
+            format!("{} new", app.new_mail),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ));
+        right.push(Span::styled(" · ", dim));
+    }
+    let position = match app.state.msg_table.selected() {
+        Some(i) if total > 0 => format!("{}/{}", i + 1, total),
+        _ => format!("-/{}", total),
+    };
+    right.push(Span::raw(position));
+    right.push(Span::styled(" · ", dim));
+    if app.offline {
+        right.push(Span::styled(
+            "OFFLINE",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    } else if app.syncing() {
+        let spinner = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+        right.push(Span::styled(
+            format!("syncing {}", spinner[app.ticks % spinner.len()]),
+            Style::default().fg(Color::Cyan),
+        ));
+    } else {
+        right.push(Span::styled(
+            match app.last_sync {
+                Some(time) => format!("synced {} ago", human_duration(time.elapsed())),
+                None => "never synced".to_string(),
+            },
+            dim,
+        ));
+    }
+    if app.watching() {
+        right.push(Span::styled(" · ", dim));
+        right.push(Span::styled("watching", Style::default().fg(Color::Green)));
+    }
+    right.push(Span::raw(" "));
+
+    let width = Spans::from(right.clone()).width() as u16;
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(width)].as_ref())
+        .split(area);
+    f.render_widget(Paragraph::new(Spans::from(left)), chunks[0]);
+    f.render_widget(Paragraph::new(Spans::from(right)), chunks[1]);
+}
+
+/// Format a duration roughly, like `5s` or `3m`.
+fn human_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
 /// Draw command line.
 pub fn draw_commands<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
     let style = if app.keymap.mode == KeyMode::Insert {
@@ -103,6 +259,15 @@ pub fn draw_commands<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
             KeyMode::Motion => Color::Blue,
             KeyMode::Insert => Color::Green,
             KeyMode::Review => Color::Yellow,
+            KeyMode::Compose => Color::Magenta,
+            KeyMode::Picker => Color::Cyan,
+            KeyMode::Palette => Color::Cyan,
+            KeyMode::Help => Color::Cyan,
+            KeyMode::Links => Color::Cyan,
+            KeyMode::Yank => Color::Yellow,
+            KeyMode::Find => Color::Green,
+            KeyMode::Visual => Color::Yellow,
+            KeyMode::Output => Color::Cyan,
         })
         .add_modifier(Modifier::BOLD);
     let chunks = Layout::default()
@@ -110,15 +275,29 @@ pub fn draw_commands<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
         .direction(Direction::Horizontal)
         .split(area);
 
-    let command = match app.keymap.mode {
-        KeyMode::Insert => &app.command_input,
+    let find = format!("/{}", app.find_input.text());
+    let command = match (&app.keymap.mode, &app.info) {
+        (KeyMode::Insert, _) => app.command_input.text(),
+        (KeyMode::Find, _) => &find,
+        (_, Some(info)) => info,
         _ => match app.curr_filter() {
             Some(Filter(filter)) => filter,
-            None => &app.command_input,
+            None => app.command_input.text(),
         },
     };
 
-    let input = Paragraph::new(command.as_str())
+    let cursor = match app.keymap.mode {
+        KeyMode::Insert => Some(app.command_input.cursor()),
+        KeyMode::Find => Some(app.find_input.cursor() + 1),
+        _ => None,
+    };
+    if let Some(cursor) = cursor {
+        let before: String = command.chars().take(cursor).collect();
+        let x = chunks[1].x + 1 + Span::raw(before).width() as u16;
+        f.set_cursor(x.min(chunks[1].right().saturating_sub(2)), chunks[1].y + 1);
+    }
+
+    let input = Paragraph::new(command)
         .block(block)
         .style(Style::default().fg(Color::Gray));
     let mode = Paragraph::new(Spans::from(Span::styled(
@@ -138,59 +317,208 @@ pub fn draw_commands<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
 
 /// Draw email content
 pub fn draw_content<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
-    let block = Block::default().borders(Borders::ALL);
-    let mut text = Text::default();
-    let finder = LinkFinder::new();
     let AppState {
         content: (content, offset),
+        message,
         review_flags: flags,
         ..
     } = &mut app.state;
 
+    let headers: Vec<Spans> = match message {
+        Some(message) => message
+            .display_headers(flags.full_headers)
+            .into_iter()
+            .map(|(key, value)| {
+                Spans::from(vec![
+                    Span::styled(
+                        format!("{}: ", key),
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(value.to_string()),
+                ])
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let attachments: Vec<Spans> = match message {
+        Some(message) => message
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(i, attachment)| {
+                let style = if i == flags.attachment {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Spans::from(vec![
+                    Span::styled(format!("{}. {}", i + 1, attachment.name()), style),
+                    Span::styled(
+                        format!("  {}  {}", attachment.mime_type, attachment.size()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    // headers take at most half of the reader, and attachments a quarter
+    let headers_height = (headers.len() as u16 + 2).min(area.height / 2);
+    let attachments_height = if attachments.is_empty() {
+        0
+    } else {
+        (attachments.len() as u16 + 2).min(area.height / 4)
+    };
+    let chunks = Layout::default()
+        .constraints(
+            [
+                Constraint::Length(headers_height),
+                Constraint::Length(attachments_height),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let headers = Paragraph::new(headers)
+        .block(Block::default().borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(headers, chunks[0]);
+
+    if !attachments.is_empty() {
+        // keep the selected attachment visible
+        let visible = attachments_height.saturating_sub(2) as usize;
+        let offset = (flags.attachment + 1).saturating_sub(visible) as u16;
+        let attachments = Paragraph::new(attachments)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Attachments "),
+            )
+            .scroll((offset, 0));
+        f.render_widget(attachments, chunks[1]);
+    }
+
+    // title the body with the part actually displayed
+    let kind = message
+        .as_ref()
+        .and_then(|m| m.body(&flags.part))
+        .map(|p| p.kind.clone());
+    let html = kind == Some(PartKind::Html) && !flags.raw_source;
+    let title = match &kind {
+        Some(kind) if !html && *kind == PartKind::Html => format!(" {} source ", kind),
+        Some(kind) => format!(" {} ", kind),
+        None => String::new(),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+
     // clear links stored before
     flags.links.clear();
 
-    for line in content.lines() {
-        // buffer string for current line
-        let mut line_string = Vec::new();
-        // collect all links in the line
-        let links: Vec<_> = finder.links(line).collect();
-
-        // label all links iff follow link mode and there is a link in the current line.
-        let mut last_link_end = 0;
-
-        for link in links {
-            // save link to AppState
-            flags.links.push(link.as_str().to_string());
-
-            // split current line into three parts:
-            // before the link, the link itself and after the link
-            let (_, rest) = line.split_at(last_link_end);
-            let (first, _) = rest.split_at(link.start() - last_link_end);
-
-            // add text before link
-            line_string.push(Span::from(first));
-
-            // check if links flag is on
-            if flags.show_links {
-                // add link text with Cyan color
-                line_string.push(Span::styled(
-                    format!("{} [{}]", link.as_str(), flags.links.len()),
-                    Style::default().fg(Color::Cyan),
-                ));
-            } else {
-                line_string.push(Span::raw(link.as_str()));
+    let mut text = if html {
+        let rendered = match flags.html.take() {
+            Some(rendered) if rendered.labeled == flags.show_links => rendered,
+            _ => {
+                let mut links = Vec::new();
+                let lines = html::render(content, &mut links, flags.show_links);
+                RenderedHtml {
+                    labeled: flags.show_links,
+                    lines,
+                    links,
+                }
             }
+        };
+        flags.links = rendered.links.clone();
+        let text = Text::from(rendered.lines.clone());
+        flags.html = Some(rendered);
+        text
+    } else {
+        plain_text(content, flags)
+    };
+
+    // keep the text of lines for searches and selections
+    let width = chunks[2].width.saturating_sub(2).max(1) as usize;
+    flags.lines = text
+        .lines
+        .iter()
+        .map(|spans| spans.0.iter().map(|span| span.content.as_ref()).collect())
+        .collect();
+    flags.line_rows = Vec::with_capacity(flags.lines.len());
+    let mut row = 0;
+    for line in &text.lines {
+        flags.line_rows.push(row);
+        // approximation of the rows taken by the wrapped line
+        row += line.width().max(1).div_ceil(width);
+    }
+
+    if let Some(query) = &flags.search {
+        let query = query.to_lowercase();
+        flags.matches = flags
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(&query))
+            .map(|(i, _)| i)
+            .collect();
+        if flags.current_match >= flags.matches.len() {
+            flags.current_match = 0;
+        }
+        for (n, i) in flags.matches.iter().enumerate() {
+            let color = if n == flags.current_match {
+                Color::LightRed
+            } else {
+                Color::Yellow
+            };
+            let ranges = match_ranges(&flags.lines[*i], &query);
+            let line = std::mem::take(&mut text.lines[*i]);
+            text.lines[*i] =
+                patch_ranges(line, &ranges, Style::default().fg(Color::Black).bg(color));
+        }
+    } else {
+        flags.matches.clear();
+    }
 
-            // update the index of the end of link
-            last_link_end = link.end();
+    if let Some((anchor, cursor)) = flags.visual {
+        let selected = Style::default().add_modifier(Modifier::REVERSED);
+        for line in text
+            .lines
+            .iter_mut()
+            .take(anchor.max(cursor) + 1)
+            .skip(anchor.min(cursor))
+        {
+            let size = line.0.iter().map(|span| span.content.len()).sum();
+            *line = match size {
+                // show empty lines as selected too
+                0 => Spans::from(Span::styled(" ", selected)),
+                _ => patch_ranges(std::mem::take(line), &[(0, size)], selected),
+            };
         }
+    }
 
-        // add the rest of the line
-        let (_, rest) = line.split_at(last_link_end);
-        line_string.push(Span::raw(rest));
-        text.extend(Text::from(Spans::from(line_string)));
+    // scroll to the current match or the visual cursor if it is not shown
+    let target = match flags.visual {
+        Some((_, cursor)) => Some(cursor),
+        None => flags.matches.get(flags.current_match).copied(),
+    };
+    if let (true, Some(target)) = (flags.jump, target) {
+        let height = chunks[2].height.saturating_sub(2) as usize;
+        let start = flags.line_rows.get(target).copied().unwrap_or(0);
+        let end = flags
+            .line_rows
+            .get(target + 1)
+            .copied()
+            .unwrap_or(start + 1);
+        if start < *offset as usize {
+            *offset = start as u16;
+        } else if end > *offset as usize + height {
+            *offset = end.saturating_sub(height) as u16;
+        }
     }
+    flags.jump = false;
 
     if flags.show_stats {
         text.extend(Text::raw(format!("Total Links: {}", flags.links.len())));
@@ -201,5 +529,513 @@ pub fn draw_content<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
         .wrap(Wrap { trim: true })
         .scroll((*offset, 0));
 
-    f.render_widget(content, area);
+    f.render_widget(content, chunks[2]);
+}
+
+/// Byte ranges of the query in the line, the query being lowercase.
+fn match_ranges(line: &str, query: &str) -> Vec<(usize, usize)> {
+    let lower = line.to_lowercase();
+    // lowercasing may change byte lengths, then highlight the whole line
+    if lower.len() != line.len() {
+        return vec![(0, line.len())];
+    }
+    lower
+        .match_indices(query)
+        .map(|(i, m)| (i, i + m.len()))
+        .collect()
+}
+
+/// Patch the style of the parts of the line in the given byte ranges.
+fn patch_ranges<'a>(line: Spans<'a>, ranges: &[(usize, usize)], style: Style) -> Spans<'a> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for span in line.0 {
+        let content = span.content.as_ref();
+        let end = start + content.len();
+        // split the span at the bounds of the ranges it overlaps
+        let mut bounds = vec![0, content.len()];
+        for (from, to) in ranges {
+            for bound in [*from, *to] {
+                if bound > start && bound < end && content.is_char_boundary(bound - start) {
+                    bounds.push(bound - start);
+                }
+            }
+        }
+        bounds.sort_unstable();
+        bounds.dedup();
+        for pair in bounds.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let inside = ranges
+                .iter()
+                .any(|(a, b)| *a <= start + from && start + to <= *b);
+            let span_style = if inside {
+                span.style.patch(style)
+            } else {
+                span.style
+            };
+            spans.push(Span::styled(content[from..to].to_string(), span_style));
+        }
+        start = end;
+    }
+    Spans::from(spans)
+}
+
+/// Colors of quoted lines, cycling by quote depth.
+const QUOTE_COLORS: [Color; 4] = [Color::Green, Color::Yellow, Color::Cyan, Color::Magenta];
+/// Quoted blocks with more lines than this are folded iff the fold flag is on.
+const QUOTE_FOLD_LINES: usize = 3;
+
+/// Build the text of a plain text body, coloring quotes and dimming the signature.
+fn plain_text<'a>(content: &'a str, flags: &mut ReviewFlags) -> Text<'a> {
+    let mut text = Text::default();
+    let finder = LinkFinder::new();
+    let lines: Vec<&str> = content.lines().collect();
+    // the signature starts at the last separator line
+    let signature = lines
+        .iter()
+        .rposition(|line| *line == "-- ")
+        .unwrap_or(lines.len());
+    let folded_style = Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::ITALIC);
+
+    let mut i = 0;
+    while i < signature {
+        if quote_depth(lines[i]) == 0 {
+            text.lines
+                .push(line_spans(&finder, lines[i], Style::default(), flags));
+            i += 1;
+            continue;
+        }
+
+        // find the end of the quoted block
+        let end = lines[i..signature]
+            .iter()
+            .position(|line| quote_depth(line) == 0)
+            .map_or(signature, |n| i + n);
+        let shown = if flags.fold && end - i > QUOTE_FOLD_LINES {
+            1
+        } else {
+            end - i
+        };
+        for line in &lines[i..i + shown] {
+            let color = QUOTE_COLORS[(quote_depth(line) - 1) % QUOTE_COLORS.len()];
+            text.lines
+                .push(line_spans(&finder, line, Style::default().fg(color), flags));
+        }
+        if shown < end - i {
+            text.lines.push(Spans::from(Span::styled(
+                format!("⋯ {} quoted lines folded", end - i - shown),
+                folded_style,
+            )));
+        }
+        i = end;
+    }
+
+    if signature < lines.len() {
+        if flags.fold {
+            text.lines.push(Spans::from(Span::styled(
+                format!(
+                    "-- ⋯ signature of {} lines folded",
+                    lines.len() - signature - 1
+                ),
+                folded_style,
+            )));
+        } else {
+            let style = Style::default().fg(Color::DarkGray);
+            for line in &lines[signature..] {
+                text.lines.push(line_spans(&finder, line, style, flags));
+            }
+        }
+    }
+
+    text
+}
+
+/// Get the quote level of a line, like 2 for `> > text` or `>> text`.
+fn quote_depth(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == '>' || *c == ' ')
+        .filter(|c| *c == '>')
+        .count()
+}
+
+/// Build a line of text with the given style, labeling links iff follow link mode.
+fn line_spans<'a>(
+    finder: &LinkFinder,
+    line: &'a str,
+    style: Style,
+    flags: &mut ReviewFlags,
+) -> Spans<'a> {
+    // buffer string for current line
+    let mut line_string = Vec::new();
+    // collect all links in the line
+    let links: Vec<_> = finder.links(line).collect();
+
+    // label all links iff follow link mode and there is a link in the current line.
+    let mut last_link_end = 0;
+
+    for link in links {
+        // save link to AppState
+        flags.links.push(link.as_str().to_string());
+
+        // split current line into three parts:
+        // before the link, the link itself and after the link
+        let (_, rest) = line.split_at(last_link_end);
+        let (first, _) = rest.split_at(link.start() - last_link_end);
+
+        // add text before link
+        line_string.push(Span::styled(first, style));
+
+        // check if links flag is on
+        if flags.show_links {
+            // add link text with Cyan color
+            line_string.push(Span::styled(
+                format!("{} [{}]", link.as_str(), flags.links.len()),
+                Style::default().fg(Color::Cyan),
+            ));
+        } else {
+            line_string.push(Span::styled(link.as_str(), style));
+        }
+
+        // update the index of the end of link
+        last_link_end = link.end();
+    }
+
+    // add the rest of the line
+    let (_, rest) = line.split_at(last_link_end);
+    line_string.push(Span::styled(rest, style));
+    Spans::from(line_string)
+}
+
+/// Draw the confirmation screen of the message being composed.
+pub fn draw_compose<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let draft = match &app.draft {
+        Some(draft) => draft,
+        None => return,
+    };
+    let key_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+
+    let headers: Vec<Spans> = draft
+        .headers
+        .iter()
+        .map(|(key, value)| {
+            Spans::from(vec![
+                Span::styled(format!("{}: ", key), key_style),
+                Span::raw(value.as_str()),
+            ])
+        })
+        .collect();
+
+    let attachments: Vec<Spans> = if draft.attachments.is_empty() {
+        vec![Spans::from(Span::styled(
+            "No attachment",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        draft
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let style = if i == draft.selected {
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Spans::from(vec![
+                    Span::styled(format!("{}. {}", i + 1, path.display()), style),
+                    Span::styled(
+                        format!("  {}", Draft::attachment_size(path)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect()
+    };
+
+    let headers_height = (headers.len() as u16 + 2).min(area.height / 3);
+    let attachments_height = (attachments.len() as u16 + 2).min(area.height / 3);
+    let chunks = Layout::default()
+        .constraints(
+            [
+                Constraint::Length(headers_height),
+                Constraint::Length(attachments_height),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let border_style = Style::default().fg(Color::Magenta);
+    let headers = Paragraph::new(headers)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(" Compose "),
+        )
+        .wrap(Wrap { trim: true });
+    let visible = attachments_height.saturating_sub(2) as usize;
+    let offset = (draft.selected + 1).saturating_sub(visible) as u16;
+    let attachments = Paragraph::new(attachments)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(" Attachments "),
+        )
+        .scroll((offset, 0));
+    let body = Paragraph::new(draft.body.as_str())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(headers, chunks[0]);
+    f.render_widget(attachments, chunks[1]);
+    f.render_widget(body, chunks[2]);
+}
+
+/// Draw the file picker popup.
+pub fn draw_picker<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let picker = match &app.picker {
+        Some(picker) => picker,
+        None => return,
+    };
+    let chunks = Layout::default()
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+    let block_style = Style::default().fg(Color::Cyan);
+
+    let input = Paragraph::new(Spans::from(vec![
+        Span::styled(
+            format!("{}/", picker.dir.display()),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw(picker.filter.as_str()),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(block_style)
+            .title(" Attach file "),
+    );
+
+    let items: Vec<ListItem> = picker
+        .matches()
+        .into_iter()
+        .map(|(name, is_dir)| {
+            if *is_dir {
+                ListItem::new(format!("{}/", name)).style(Style::default().fg(Color::Blue))
+            } else {
+                ListItem::new(name.as_str())
+            }
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(block_style),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">");
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, chunks[0]);
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+/// Draw the command palette popup.
+pub fn draw_palette<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let palette = match &app.palette {
+        Some(palette) => palette,
+        None => return,
+    };
+    let chunks = Layout::default()
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+    let block_style = Style::default().fg(Color::Cyan);
+
+    let input = Paragraph::new(palette.filter.as_str()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(block_style)
+            .title(" Commands "),
+    );
+
+    let matches = palette.matches();
+    let name_width = matches
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = matches
+        .into_iter()
+        .map(|entry| {
+            ListItem::new(Spans::from(vec![
+                Span::raw(format!("{:width$}  ", entry.name, width = name_width)),
+                Span::styled(
+                    format!("{:12}  ", entry.keys),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(entry.description, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(palette.selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(block_style),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">");
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, chunks[0]);
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+/// Draw the keybindings of a mode in a popup.
+pub fn draw_help<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let (mode, scroll) = match &app.help {
+        Some(help) => help,
+        None => return,
+    };
+    let keybinds = app.keymap.keybinds(mode);
+    let keys: Vec<String> = keybinds.iter().map(|keybind| keybind.to_string()).collect();
+    let events: Vec<String> = keybinds
+        .iter()
+        .map(|keybind| format!("{:?}", keybind.event()))
+        .collect();
+    let key_width = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+    let event_width = events.iter().map(|e| e.chars().count()).max().unwrap_or(0);
+
+    let lines: Vec<Spans> = keybinds
+        .iter()
+        .zip(keys.iter().zip(events.iter()))
+        .map(|(keybind, (key, event))| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:width$}  ", key, width = key_width),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(format!("{:width$}  ", event, width = event_width)),
+                Span::styled(
+                    keybind.event().description(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+
+    let help = Paragraph::new(lines).scroll((*scroll, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(format!(" Help: {} ", mode)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+/// Draw the output of the command the message was piped to.
+pub fn draw_pipe_output<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let output = match &app.pipe_output {
+        Some(output) => output,
+        None => return,
+    };
+    let color = if output.success {
+        Color::Cyan
+    } else {
+        Color::Red
+    };
+    let text = if output.text.is_empty() {
+        "(no output)"
+    } else {
+        &output.text
+    };
+
+    let paragraph = Paragraph::new(text).scroll((output.scroll, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color))
+            .title(format!(" | {} ", output.command)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// Draw the links of the message with their hint labels.
+pub fn draw_link_picker<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let picker = match &app.link_picker {
+        Some(picker) => picker,
+        None => return,
+    };
+
+    let items: Vec<ListItem> = picker
+        .labels
+        .iter()
+        .zip(picker.links.iter())
+        .enumerate()
+        .map(|(i, (label, link))| {
+            // dim the links which cannot match the typed label anymore
+            let style = if label.starts_with(&picker.typed) {
+                Style::default()
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(
+                    format!("{} ", label),
+                    style.fg(Color::Black).bg(Color::Yellow),
+                ),
+                Span::styled(
+                    format!(" [{}] ", i + 1),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(link.as_str(), style),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(" Links: o open, c copy, y yank "),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">");
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
 }
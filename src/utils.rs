@@ -1,61 +1,324 @@
-use std::io::prelude::*;
-use std::process::{Command, Stdio};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
-use crate::app::data::Msg;
+use serde_json::Value;
 
-pub fn get_email_list() -> String {
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg("(himalaya --output json list -s 0)")
+use crate::{config::Config, version::Version};
+
+/// Build a himalaya command running the subcommand with JSON output and the global flags
+/// of the configuration, on its account and on its folder if the subcommand acts on one.
+///
+/// Arguments are passed to the process as they are, without going through a shell.
+pub fn himalaya(
+    config: &Config,
+    version: Option<Version>,
+    subcommand: &[&str],
+    in_folder: bool,
+) -> Command {
+    let version = version.unwrap_or(Version::LEGACY);
+    // global flags before 1.0 apply to any subcommand
+    let folder = config
+        .chosen_folder()
+        .filter(|_| in_folder || !version.groups_subcommands());
+    let scope = version.scope_args(config.himalaya_account(), folder);
+
+    let mut command = Command::new(&config.himalaya);
+    command
+        .args(["--output", "json"])
+        .args(config.global_args());
+    if version.groups_subcommands() {
+        command.args(subcommand).args(scope);
+    } else {
+        command.args(scope).args(subcommand);
+    }
+    command
+}
+
+/// Words of the errors of himalaya failing to reach the server.
+const CONNECTION_ERRORS: [&str; 5] = ["connect", "resolve", "timed out", "unreachable", "network"];
+
+/// Run the command and get its output, or its error output if it fails.
+///
+/// Errors are `NotConnected` when the program cannot run or cannot reach the server,
+/// which makes the app go offline.
+pub fn output(command: &mut Command) -> io::Result<String> {
+    let output = command
         .output()
-        .expect("failed to execute himalaya");
-    String::from_utf8(output.stdout).unwrap()
+        .map_err(|e| io::Error::new(io::ErrorKind::NotConnected, e))?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+    let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let lower = error.to_lowercase();
+    if CONNECTION_ERRORS.iter().any(|word| lower.contains(word)) {
+        Err(io::Error::new(io::ErrorKind::NotConnected, error))
+    } else {
+        Err(io::Error::other(error))
+    }
+}
+
+/// Build the command watching the folder for changes.
+pub fn watch_command(config: &Config, version: Option<Version>) -> Command {
+    match config.idle_command.split_first() {
+        Some((program, args)) => {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+        None => himalaya(
+            config,
+            version,
+            version.unwrap_or(Version::LEGACY).watch_args(),
+            true,
+        ),
+    }
+}
+
+/// List envelopes of the folder, with the subcommand of the himalaya version if known.
+pub fn get_email_list(config: &Config, version: Option<Version>) -> io::Result<String> {
+    let args = version.unwrap_or(Version::LEGACY).list_args();
+    output(&mut himalaya(config, version, args, true))
+}
+
+/// Get the payload of a JSON output, wrapped in `response` before himalaya 0.8.
+pub fn response(value: &Value) -> &Value {
+    value.get("response").unwrap_or(value)
+}
+
+/// Get the names of the folders of the account.
+pub fn get_folders(config: &Config, version: Option<Version>) -> io::Result<Vec<String>> {
+    let args = version.unwrap_or(Version::LEGACY).folders_args();
+    let output = output(&mut himalaya(config, version, args, false))?;
+    let value: Value = serde_json::from_str(&output)?;
+    Ok(response(&value)
+        .as_array()
+        .map(|folders| {
+            folders
+                .iter()
+                .filter_map(|folder| folder.get("name")?.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Get a template for a new message from himalaya, with headers of the account filled.
+pub fn get_template(config: &Config, version: Option<Version>) -> String {
+    let args = version.unwrap_or(Version::LEGACY).template_args();
+    template(himalaya(config, version, args, false))
+        .unwrap_or_else(|| "To: \nSubject: \n\n".to_string())
+}
+
+/// Get a template replying to the message with the given id from himalaya.
+pub fn get_reply_template(config: &Config, version: Option<Version>, id: usize) -> Option<String> {
+    let mut command = himalaya(config, version, &["template", "reply"], true);
+    command.arg(id.to_string());
+    template(command)
+}
+
+/// Get the template printed by himalaya, as a JSON string or as is since 1.0.
+fn template(mut command: Command) -> Option<String> {
+    let output = output(&mut command).ok()?;
+    match serde_json::from_str::<Value>(&output) {
+        Ok(value) => response(&value).as_str().map(|s| s.to_string()),
+        Err(_) => Some(output),
+    }
+}
+
+/// Send a raw message through himalaya.
+pub fn send_message(config: &Config, version: Option<Version>, raw: &[u8]) -> io::Result<()> {
+    let args = version.unwrap_or(Version::LEGACY).send_args();
+    let mut child = himalaya(config, version, args, false)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(raw)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Score how well the pattern matches the text as a case insensitive subsequence,
+/// favoring consecutive chars and matches at the start of words.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut chars = text.chars().enumerate().peekable();
+    let mut prev_match: Option<usize> = None;
+    let mut prev_char = None;
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (i, c) = chars.next()?;
+            let boundary = match prev_char {
+                None => true,
+                Some(prev) => {
+                    !char::is_alphanumeric(prev) || (prev.is_lowercase() && c.is_uppercase())
+                }
+            };
+            prev_char = Some(c);
+            if c.to_lowercase().eq(std::iter::once(p)) {
+                score += 1;
+                if prev_match.is_some_and(|j| j + 1 == i) {
+                    score += 5;
+                }
+                if boundary {
+                    score += 3;
+                }
+                prev_match = Some(i);
+                break;
+            }
+        }
+    }
+    // prefer shorter texts among equal matches
+    Some(score * 100 - text.len() as i64)
+}
+
+/// Format a size in bytes for humans.
+pub fn human_size(bytes: usize) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Path of a file with the given name in the directory which does not exist yet,
+/// inserting a counter before the extension if needed, like `file-1.pdf`.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let name = PathBuf::from(name);
+    let mut path = dir.join(&name);
+    let mut i = 1;
+    while path.exists() {
+        let mut numbered = name.file_stem().unwrap_or_default().to_os_string();
+        numbered.push(format!("-{}", i));
+        if let Some(ext) = name.extension() {
+            numbered.push(".");
+            numbered.push(ext);
+        }
+        path = dir.join(numbered);
+        i += 1;
+    }
+    path
+}
+
+/// Create a new directory in the temporary directory that only the user can read.
+///
+/// Creation fails on existing paths, so a directory made by someone else is never used.
+pub fn private_temp_dir(prefix: &str) -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    let mut i = 0;
+    loop {
+        let dir = std::env::temp_dir().join(format!("{}-{}-{}", prefix, std::process::id(), i));
+        match builder.create(&dir) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => i += 1,
+            result => return result.map(|_| dir),
+        }
+    }
 }
 
-pub fn filter<T: Clone>(data: &Vec<T>) -> Vec<T> {
-    let data_strings = data
-        .iter()
-        .map(|x| x.to_string())
-        .fold(String::new(), |a, b| a + b + "\n");
+/// Convert days since the epoch to a civil date, as year, month and day.
+pub fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
 
-    return data.to_vec();
+/// Format seconds since the epoch like the dates of himalaya envelopes, `2022-03-01 10:00` in UTC.
+pub fn format_timestamp(secs: i64) -> String {
+    let (year, month, day) = civil_date(secs.div_euclid(86400));
+    let rem = secs.rem_euclid(86400);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60
+    )
 }
 
+/// Keywords of himalaya search queries, other queries are searched in subjects.
+///
+/// Flags are searched with their name, or their name prefixed by `un` for messages without it.
+pub const SEARCH_KEYWORDS: [&str; 21] = [
+    "all",
+    "answered",
+    "before",
+    "body",
+    "deleted",
+    "draft",
+    "flagged",
+    "from",
+    "header",
+    "new",
+    "not",
+    "or",
+    "recent",
+    "seen",
+    "subject",
+    "text",
+    "to",
+    "unanswered",
+    "undeleted",
+    "unflagged",
+    "unseen",
+];
+
 /// Process himalaya command and return in JSON format string.
-pub fn himalaya_command(command: Vec<String>) -> String {
+pub fn himalaya_command(config: &Config, command: Vec<String>) -> io::Result<String> {
     let mut iter = command.iter();
-    let mut line = String::from("himalaya --output json");
-    let mut options = "";
+    let mut args: Vec<&str> = Vec::new();
+    let mut options: &[&str] = &[];
 
     if let Some(first_arg) = iter.next() {
         match first_arg.to_uppercase().as_str() {
             "SEARCH" => {
+                args.push("search");
                 if let Some(first) = command.get(1) {
-                    let keywords = [
-                        "all", "answered", "before", "body", "deleted", "from", "header", "new",
-                        "not", "or", "recent", "seen", "subject", "text", "to",
-                    ];
-                    if !keywords.contains(&first.to_lowercase().as_str()) {
-                        line = format!("{} search subject", line);
+                    if !SEARCH_KEYWORDS.contains(&first.to_lowercase().as_str()) {
+                        args.push("subject");
                     }
                     // get all emails satified the filter
-                    options = "-s 0"
+                    options = &["-s", "0"];
                 }
             }
-            "READ" => line = format!("{} read", line),
-            _ => {}
+            "READ" => args.push("read"),
+            "TEMPLATE" => args.push("template"),
+            _ => args.push(first_arg),
         }
     }
 
-    for arg in iter {
-        line = format!("{} {}", line, arg);
-    }
-
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(format!("{} {}", line, options))
-        .output()
-        .expect("failed to execute himalaya");
+    args.extend(iter.map(|arg| arg.as_str()));
+    args.extend(options);
 
-    String::from_utf8(output.stdout).unwrap()
+    let (subcommand, args) = args.split_at(1.min(args.len()));
+    output(himalaya(config, None, subcommand, true).args(args))
 }
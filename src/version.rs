@@ -0,0 +1,114 @@
+use std::{fmt, process::Command};
+
+use crate::config::Config;
+
+/// Version of himalaya, which decides the subcommands used.
+///
+/// Outputs of all versions are read the same way, see `app::data`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Find the version in the output of `himalaya --version`, like `himalaya 0.5.10`
+    /// or `himalaya v1.0.0 +imap +smtp`.
+    pub fn parse(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|word| {
+            let mut numbers = word
+                .trim_start_matches('v')
+                .split(['.', '-', '+'])
+                .map(|n| n.parse::<u32>());
+            let major = numbers.next()?.ok()?;
+            let minor = numbers.next()?.ok()?;
+            let patch = numbers.next().and_then(|n| n.ok()).unwrap_or(0);
+            Some(Version::new(major, minor, patch))
+        })
+    }
+
+    /// Ask the himalaya binary of the configuration for its version.
+    pub fn detect(config: &Config) -> Option<Self> {
+        let output = Command::new(&config.himalaya)
+            .arg("--version")
+            .output()
+            .ok()?;
+        Version::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Oldest version supported, assumed when it cannot be detected.
+    pub const LEGACY: Version = Version::new(0, 5, 0);
+
+    /// Arguments listing all envelopes of the folder.
+    pub fn list_args(&self) -> &'static [&'static str] {
+        self.pick(&["envelope", "list", "-s", "0"], &["list", "-s", "0"])
+    }
+
+    /// Arguments listing the folders of the account.
+    pub fn folders_args(&self) -> &'static [&'static str] {
+        self.pick(&["folder", "list"], &["mailboxes"])
+    }
+
+    /// Arguments waiting for changes of the folder.
+    pub fn watch_args(&self) -> &'static [&'static str] {
+        self.pick(&["envelope", "watch"], &["watch"])
+    }
+
+    /// Arguments getting the template of a new message.
+    pub fn template_args(&self) -> &'static [&'static str] {
+        self.pick(&["template", "write"], &["template", "new"])
+    }
+
+    /// Arguments sending the raw message given on stdin.
+    pub fn send_args(&self) -> &'static [&'static str] {
+        self.pick(&["message", "send"], &["send"])
+    }
+
+    /// Whether subcommands are grouped by the object they act on, and take the account
+    /// and the folder as their own flags, which is the case since 1.0.
+    pub fn groups_subcommands(&self) -> bool {
+        *self >= Version::new(1, 0, 0)
+    }
+
+    /// Flags selecting the account and the folder, global flags before 1.0 and flags of
+    /// the subcommand since.
+    pub fn scope_args(&self, account: Option<&str>, folder: Option<&str>) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(account) = account {
+            args.extend(["--account".to_string(), account.to_string()]);
+        }
+        if let Some(folder) = folder {
+            let flag = self.pick(&["--folder"], &["--mailbox"]);
+            args.extend(flag.iter().map(|flag| flag.to_string()));
+            args.push(folder.to_string());
+        }
+        args
+    }
+
+    fn pick(
+        &self,
+        grouped: &'static [&'static str],
+        legacy: &'static [&'static str],
+    ) -> &'static [&'static str] {
+        if self.groups_subcommands() {
+            grouped
+        } else {
+            legacy
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+/// Event sent by a watch process.
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The folder changed on the server.
+    Changed,
+    /// The process exited, with its last output if any.
+    Exited(String),
+}
+
+/// Long-running process pushing folder changes, like `himalaya watch` waiting on IMAP IDLE.
+///
+/// Every line printed by the process on stdout is considered as a change notification,
+/// logs and warnings on stderr are not.
+#[derive(Debug)]
+pub struct Watcher {
+    child: Child,
+    rx: Receiver<WatchEvent>,
+}
+
+impl Watcher {
+    pub fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (tx, rx) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, tx.clone(), false);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            // only the end of stderr tells that the process exited
+            forward_lines(stderr, tx, true);
+        }
+
+        Ok(Watcher { child, rx })
+    }
+
+    /// Get the next pending event without blocking.
+    pub fn try_next(&mut self) -> Option<WatchEvent> {
+        match self.rx.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(WatchEvent::Exited(String::new())),
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Send a change event for each line read from stdout, or keep the last line of stderr
+/// to send with the exit event at its end.
+fn forward_lines<R: Read + Send + 'static>(reader: R, tx: Sender<WatchEvent>, stderr: bool) {
+    thread::spawn(move || {
+        let mut last = String::new();
+        for line in BufReader::new(reader).lines() {
+            match line {
+                Ok(line) if stderr => last = line,
+                Ok(_) => {
+                    if tx.send(WatchEvent::Changed).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        if stderr {
+            let _ = tx.send(WatchEvent::Exited(last));
+        }
+    });
+}
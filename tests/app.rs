@@ -0,0 +1,477 @@
+mod common;
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, Stub};
+use himalaya_tui::{
+    config::Config,
+    keymap::{Event, KeyMode},
+    mail::BackendKind,
+};
+use mailparse::MailHeaderMap;
+
+fn inbox(name: &str) -> Stub {
+    Stub::new(name)
+        .fixture("list", "list.json")
+        .fixture("read-1", "read-1.json")
+}
+
+/// Select the second envelope, the message with id 1, and read it.
+fn read_welcome(harness: &mut Harness) {
+    harness.send([Event::SelectNextMsg, Event::SelectNextMsg, Event::ReviewMsg]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+}
+
+#[test]
+fn list_shows_envelopes() {
+    let mut harness = Harness::start(inbox("list"));
+    assert!(!harness.app.offline);
+    assert_eq!(harness.app.emails.len(), 2);
+    harness.assert_shows("Release plan");
+    harness.assert_shows("bob@example.com");
+    harness.assert_shows("2 messages");
+}
+
+#[test]
+fn offline_without_himalaya() {
+    let mut harness = Harness::start(Stub::new("offline"));
+    assert!(harness.app.offline);
+    harness.assert_shows("OFFLINE");
+}
+
+#[test]
+fn read_and_leave_message() {
+    let mut harness = Harness::start(inbox("read"));
+    read_welcome(&mut harness);
+    harness.assert_shows("Subject: Welcome aboard");
+    harness.assert_shows("See you soon");
+    assert!(harness.stub.calls().contains("read 1 --raw"));
+
+    harness.send([Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Motion);
+    harness.assert_hides("See you soon");
+    harness.assert_shows("Release plan");
+}
+
+#[test]
+fn unreadable_message_shows_error() {
+    let mut harness = Harness::start(Stub::new("unreadable").fixture("list", "list.json"));
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Motion);
+    harness.assert_shows("Cannot read message: cannot read");
+    // himalaya answered, so it is still online
+    assert!(!harness.app.offline);
+}
+
+#[test]
+fn unparsable_message_shows_error() {
+    let raw = "Subject: Broken\r\nContent-Transfer-Encoding: base64\r\n\r\n!!!\r\n";
+    let stub = inbox("unparsable").output("read-1", &format!("{{\"response\": {:?}}}", raw));
+    let mut harness = Harness::start(stub);
+    harness.send([Event::SelectNextMsg, Event::SelectNextMsg, Event::ReviewMsg]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Motion);
+    harness.assert_shows("Cannot parse message");
+}
+
+#[test]
+fn toggle_folded_signature() {
+    let mut harness = Harness::start(inbox("fold"));
+    read_welcome(&mut harness);
+    harness.send([Event::ToggleFold]);
+    harness.assert_shows("signature of 1 lines folded");
+    harness.send([Event::ToggleFold]);
+    harness.assert_hides("folded");
+}
+
+#[test]
+fn pick_link_with_hint() {
+    let mut harness = Harness::start(inbox("links"));
+    read_welcome(&mut harness);
+    harness.send([Event::ShowLinks]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Links);
+    harness.assert_shows("https://wiki.example.com");
+
+    harness.type_text("s");
+    let picker = harness.app.link_picker.as_ref().unwrap();
+    assert_eq!(picker.selected(), Some("https://wiki.example.com"));
+
+    harness.send([Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    assert!(harness.app.link_picker.is_none());
+}
+
+#[test]
+fn follow_out_of_bounds_does_not_panic() {
+    let mut harness = Harness::start(inbox("follow"));
+    read_welcome(&mut harness);
+    for command in ["follow 0", "follow 9", "follow"] {
+        harness.send([Event::SwitchMode(KeyMode::Insert)]);
+        harness.type_text(command);
+        harness.send([Event::Submit]);
+        assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+        harness.assert_shows("No such link");
+    }
+}
+
+#[test]
+fn search_in_message() {
+    let mut harness = Harness::start(inbox("find"));
+    read_welcome(&mut harness);
+    harness.send([Event::StartSearch]);
+    harness.type_text("example");
+    harness.assert_shows("/example");
+    harness.send([Event::Submit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+
+    let flags = &harness.app.state.review_flags;
+    assert_eq!(flags.matches.len(), 1);
+    harness.send([Event::NextMatch]);
+    assert_eq!(harness.app.state.review_flags.current_match, 0);
+}
+
+#[test]
+fn select_lines_in_visual_mode() {
+    let mut harness = Harness::start(inbox("visual"));
+    read_welcome(&mut harness);
+    harness.send([Event::StartVisual, Event::SelectNext, Event::SelectNext]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Visual);
+    assert_eq!(
+        harness.app.state.selection(),
+        [
+            "Hello,",
+            "",
+            concat!(
+                "The handbook is at https://example.com/handbook ",
+                "and the wiki at https://wiki.example.com."
+            )
+        ]
+    );
+
+    harness.send([Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    assert!(harness.app.state.selection().is_empty());
+}
+
+#[test]
+fn edit_command_line() {
+    let mut harness = Harness::start(inbox("line"));
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("serch seen");
+    harness.send([Event::CursorStart, Event::CursorRight, Event::CursorRight]);
+    harness.type_text("a");
+    assert_eq!(harness.app.command_input.text(), "search seen");
+
+    harness.send([Event::CursorEnd, Event::DeleteWord]);
+    assert_eq!(harness.app.command_input.text(), "search ");
+    harness.type_text("ans");
+    harness.send([Event::Complete]);
+    assert_eq!(harness.app.command_input.text(), "search answered");
+
+    harness.send([Event::DeleteToStart]);
+    assert_eq!(harness.app.command_input.text(), "");
+    harness.send([Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Motion);
+}
+
+#[test]
+fn complete_senders_and_flags() {
+    let list = r#"{"response": [
+        {"id": 2, "flags": [], "subject": "Plan", "date": "",
+         "sender": "Alice Smith <alice@example.com>"},
+        {"id": 1, "flags": [{"Custom": "work"}], "subject": "Hi", "date": "",
+         "sender": "Bob Stone"}
+    ]}"#;
+    let mut harness = Harness::start(Stub::new("complete").output("list", list));
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("search from al");
+    harness.send([Event::Complete]);
+    assert_eq!(
+        harness.app.command_input.text(),
+        "search from alice@example.com"
+    );
+
+    harness.send([Event::DeleteToStart]);
+    harness.type_text("search to bo");
+    harness.send([Event::Complete]);
+    assert_eq!(harness.app.command_input.text(), "search to \"Bob Stone\"");
+
+    harness.send([Event::DeleteToStart]);
+    harness.type_text("search unf");
+    harness.send([Event::Complete]);
+    assert_eq!(harness.app.command_input.text(), "search unflagged");
+
+    harness.send([Event::DeleteToStart]);
+    harness.type_text("tag -w");
+    harness.send([Event::Complete]);
+    assert_eq!(harness.app.command_input.text(), "tag -work");
+    harness.send([Event::DeleteToStart]);
+    harness.type_text("tag +fl");
+    harness.send([Event::Complete]);
+    assert_eq!(harness.app.command_input.text(), "tag +flagged");
+}
+
+#[test]
+fn recall_command_history() {
+    let mut harness = Harness::start(inbox("history"));
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("read 1");
+    harness.send([Event::Submit, Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Motion);
+
+    harness.send([Event::SwitchMode(KeyMode::Insert), Event::HistoryPrev]);
+    assert_eq!(harness.app.command_input.text(), "read 1");
+    harness.send([Event::HistoryNext]);
+    assert_eq!(harness.app.command_input.text(), "");
+    let history = fs::read_to_string(harness.stub.dir.join("history")).unwrap();
+    assert_eq!(history, "read 1\n");
+}
+
+#[test]
+fn run_action_from_palette() {
+    let mut harness = Harness::start(inbox("palette"));
+    harness.send([Event::OpenPalette]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Palette);
+    harness.type_text("command line");
+    harness.assert_shows("Open the command line");
+
+    harness.send([Event::Submit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Insert);
+    assert!(harness.app.palette.is_none());
+}
+
+#[test]
+fn help_lists_keybindings_of_mode() {
+    let mut harness = Harness::start(inbox("help"));
+    harness.send([Event::ShowHelp]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Help);
+    harness.assert_shows("Help: MOTION");
+    harness.assert_shows("Read the selected message");
+
+    harness.send([Event::Quit]);
+    read_welcome(&mut harness);
+    harness.send([Event::ShowHelp]);
+    harness.assert_shows("Help: REVIEW");
+    harness.assert_shows("Search in the message");
+}
+
+/// Inbox with a single message of id 1 read from the given raw source.
+fn message(name: &str, raw: &str) -> Stub {
+    Stub::new(name)
+        .fixture("list", "list.json")
+        .output("read-1", &format!("{{\"response\": {:?}}}", raw))
+}
+
+#[test]
+fn switch_to_rendered_html() {
+    let raw = concat!(
+        "Subject: Agenda\r\n",
+        "Content-Type: multipart/alternative; boundary=b\r\n\r\n",
+        "--b\r\nContent-Type: text/plain\r\n\r\nPlain agenda\r\n",
+        "--b\r\nContent-Type: text/html\r\n\r\n",
+        "<html><head><title>Hidden title</title></head><body>",
+        "<h1>Agenda</h1><ul><li>Budget &amp; plan</li></ul>",
+        "<p>Read <a href=\"https://example.com/notes\">the notes</a></p></body></html>\r\n",
+        "--b--\r\n",
+    );
+    let mut harness = Harness::start(message("html", raw));
+    read_welcome(&mut harness);
+    harness.assert_shows("Plain agenda");
+
+    harness.send([Event::TogglePart]);
+    harness.assert_hides("Plain agenda");
+    harness.assert_hides("Hidden title");
+    harness.assert_shows("Agenda");
+    harness.assert_shows("Budget & plan");
+    harness.assert_shows("https://example.com/notes");
+}
+
+#[test]
+fn save_attachment_to_downloads() {
+    let raw = concat!(
+        "Subject: Notes\r\n",
+        "Content-Type: multipart/mixed; boundary=b\r\n\r\n",
+        "--b\r\nContent-Type: text/plain\r\n\r\nSee the notes\r\n",
+        "--b\r\nContent-Type: text/plain\r\n",
+        "Content-Disposition: attachment; filename=notes.txt\r\n\r\n",
+        "first note\r\n",
+        "--b--\r\n",
+    );
+    let mut harness = Harness::start(message("attachment", raw));
+    read_welcome(&mut harness);
+    harness.assert_shows("notes.txt");
+
+    harness.send([Event::SaveAttachment]);
+    harness.assert_shows("Saved");
+    let saved = harness.stub.dir.join("downloads").join("notes.txt");
+    assert_eq!(fs::read_to_string(saved).unwrap(), "first note\r\n");
+}
+
+#[test]
+fn send_composed_message() {
+    let stub = Stub::new("compose")
+        .fixture("list", "list.json")
+        .output("template", r#"{"response": "To: \nSubject: \n\n"}"#)
+        .output("send", "{}");
+    let attachment = stub.dir.join("report.txt");
+    fs::write(&attachment, "numbers").unwrap();
+    let mut harness = Harness::start(stub);
+
+    // edit the draft in place of the editor
+    harness.send([Event::Compose]);
+    assert!(harness.app.interactive.take().is_some());
+    let path = harness.app.draft.as_ref().unwrap().path.clone();
+    // the draft is private to the user
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(mode(path.parent().unwrap()), 0o700);
+    fs::write(
+        &path,
+        "To: bob@example.com\nSubject: Café\n\nSee the report\n",
+    )
+    .unwrap();
+    harness.app.on_resume(Command::new("true").status());
+    assert_eq!(harness.app.keymap.mode, KeyMode::Compose);
+    harness
+        .app
+        .draft
+        .as_mut()
+        .unwrap()
+        .attachments
+        .push(attachment);
+    harness.assert_shows("report.txt");
+
+    harness.send([Event::SendDraft]);
+    harness.assert_shows("Message sent");
+    assert!(harness.app.draft.is_none());
+    let sent = fs::read(harness.stub.dir.join("sent.eml")).unwrap();
+    let sent = mailparse::parse_mail(&sent).unwrap();
+    assert_eq!(
+        sent.headers.get_first_value("To").unwrap(),
+        "bob@example.com"
+    );
+    assert_eq!(sent.headers.get_first_value("Subject").unwrap(), "Café");
+    assert_eq!(sent.subparts.len(), 2);
+    assert_eq!(
+        sent.subparts[0].get_body().unwrap().trim(),
+        "See the report"
+    );
+    assert_eq!(sent.subparts[1].get_body().unwrap(), "numbers");
+}
+
+#[test]
+fn read_cached_message_offline() {
+    let mut online = Harness::start(inbox("cache-online"));
+    read_welcome(&mut online);
+
+    let offline = Stub::new("cache-offline");
+    let config = Config {
+        cache_dir: online.stub.dir.join("cache"),
+        ..offline.config()
+    };
+    let mut harness = Harness::with_config(config, offline);
+    assert!(harness.app.offline);
+    harness.assert_shows("OFFLINE");
+    harness.assert_shows("Release plan");
+    read_welcome(&mut harness);
+    harness.assert_shows("See you soon");
+}
+
+#[test]
+fn cache_is_kept_per_backend() {
+    let online = Harness::start(inbox("cache-himalaya"));
+    let cache = online.stub.dir.join("cache");
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&cache.join("himalaya").join("default")), 0o700);
+
+    // a maildir which cannot be read shows nothing, not the envelopes of himalaya
+    let stub = Stub::new("cache-maildir");
+    let config = Config {
+        backend: BackendKind::Maildir,
+        maildir: Some(stub.dir.join("missing")),
+        cache_dir: cache,
+        ..stub.config()
+    };
+    let mut harness = Harness::with_config(config, stub);
+    assert!(harness.app.offline);
+    assert!(harness.app.emails.is_empty());
+    harness.assert_hides("Release plan");
+}
+
+/// Stub whose notify command appends the subjects of new mail to `notified`.
+fn notifying(name: &str) -> Harness {
+    let stub = inbox(name);
+    let mut config = stub.config();
+    config.notify.command = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        format!(
+            "echo \"$HIMALAYA_SUBJECT\" >> {}",
+            stub.dir.join("notified").display()
+        ),
+    ];
+    Harness::with_config(config, stub)
+}
+
+/// Give the list a new unseen message, and sync.
+fn receive_fresh_news(harness: &mut Harness) {
+    let list = r#"{"response": [
+        {"id": 3, "flags": [], "subject": "Fresh news", "sender": "carol@example.com",
+         "date": "2022-03-02 08:00:00"},
+        {"id": 2, "flags": [], "subject": "Release plan", "sender": "alice@example.com",
+         "date": "2022-03-01 10:00:00"}
+    ]}"#;
+    fs::write(harness.stub.dir.join("list.json"), list).unwrap();
+    harness.app.sync();
+    harness.wait_sync();
+}
+
+/// Subjects notified once the commands run in background are done.
+fn notified(harness: &Harness) -> String {
+    let log = harness.stub.dir.join("notified");
+    let start = Instant::now();
+    let mut notified = String::new();
+    while !notified.ends_with('\n') && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+        notified = fs::read_to_string(&log).unwrap_or_default();
+    }
+    // give extra runs a chance to show up
+    thread::sleep(Duration::from_millis(100));
+    fs::read_to_string(&log).unwrap_or_default()
+}
+
+#[test]
+fn notify_new_unseen_mail() {
+    let mut harness = notifying("notify");
+    assert_eq!(harness.app.new_mail, 0);
+    receive_fresh_news(&mut harness);
+    assert_eq!(harness.app.new_mail, 1);
+    harness.assert_shows("Fresh news");
+    assert_eq!(notified(&harness), "Fresh news\n");
+}
+
+#[test]
+fn notify_once_while_searching() {
+    let mut harness = notifying("notify-search");
+    let results = r#"{"response": [{"id": 2, "flags": [], "subject": "Release plan",
+        "sender": "alice@example.com", "date": "2022-03-01 10:00:00"}]}"#;
+    fs::write(harness.stub.dir.join("search.json"), results).unwrap();
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("search release");
+    harness.send([Event::Submit]);
+    assert_eq!(harness.app.emails.len(), 1);
+
+    receive_fresh_news(&mut harness);
+    harness.app.sync();
+    harness.wait_sync();
+    // the search results stay, and the message is only new once
+    harness.assert_hides("Fresh news");
+    assert_eq!(harness.app.new_mail, 1);
+    assert_eq!(notified(&harness), "Fresh news\n");
+}
@@ -0,0 +1,218 @@
+//! Headless harness driving the app against a stub himalaya and a test terminal.
+
+// each test crate uses only part of the harness
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use himalaya_tui::{
+    app::{data::Msg, App},
+    config::Config,
+    keymap::Event,
+    ui,
+};
+use tui::{backend::TestBackend, Terminal};
+
+/// Stub of himalaya answering each subcommand with the content of `<subcommand>.json`,
+/// or `read-<id>.json` for messages, from its directory. Messages sent are kept in `sent.eml`.
+const STUB: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+# the output format is given to all versions
+if [ "$1" = "--output" ]; then
+    shift 2
+fi
+echo "$@" >> "$dir/calls"
+# himalaya 1.0 takes the account and folder after the subcommand, older versions before
+case "$1" in
+    -a|--account|-m|--mailbox)
+        if grep -q v1 "$dir/--version.json" 2>/dev/null; then
+            echo "unexpected argument '$1'" >&2
+            exit 2
+        fi
+        ;;
+esac
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -a|--account|-m|--mailbox) shift 2 ;;
+        *) break ;;
+    esac
+done
+case "$1" in
+    read) file="$dir/read-$2.json" ;;
+    send) cat > "$dir/sent.eml"; file="$dir/send.json" ;;
+    *) file="$dir/$1.json" ;;
+esac
+if [ -f "$file" ]; then
+    cat "$file"
+else
+    echo "cannot $1" >&2
+    exit 1
+fi
+"#;
+
+/// Path of a file of `tests/fixtures`.
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Envelope of an unseen message without attachment.
+pub fn envelope(id: usize, subject: &str, sender: &str) -> Msg {
+    Msg {
+        id,
+        flags: Vec::new(),
+        subject: subject.to_string(),
+        sender: sender.to_string(),
+        date: "2022-03-04 16:30".to_string(),
+        has_attachment: false,
+    }
+}
+
+/// Directory of a stub himalaya with its canned outputs.
+pub struct Stub {
+    pub dir: PathBuf,
+}
+
+impl Stub {
+    pub fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("himalaya-tui-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("himalaya");
+        fs::write(&script, STUB).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        Stub { dir }
+    }
+
+    /// Answer the subcommand with the given output.
+    pub fn output(self, subcommand: &str, output: &str) -> Self {
+        fs::write(self.dir.join(format!("{}.json", subcommand)), output).unwrap();
+        self
+    }
+
+    /// Answer the subcommand with a fixture of `tests/fixtures`.
+    pub fn fixture(self, subcommand: &str, fixture: &str) -> Self {
+        let output = fs::read_to_string(fixture_path(fixture)).unwrap();
+        self.output(subcommand, &output)
+    }
+
+    /// Configuration using the stub, keeping all files in its directory.
+    pub fn config(&self) -> Config {
+        Config {
+            himalaya: self.dir.join("himalaya"),
+            cache_dir: self.dir.join("cache"),
+            downloads_dir: self.dir.join("downloads"),
+            history_file: self.dir.join("history"),
+            poll_interval: 0,
+            ..Config::default()
+        }
+    }
+
+    /// Subcommands the stub was called with, one per line.
+    pub fn calls(&self) -> String {
+        fs::read_to_string(self.dir.join("calls")).unwrap_or_default()
+    }
+}
+
+impl Drop for Stub {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+pub struct Harness {
+    pub app: App,
+    pub terminal: Terminal<TestBackend>,
+    pub stub: Stub,
+}
+
+impl Harness {
+    /// Start the app on a terminal of 100x30 and wait for the first sync.
+    pub fn start(stub: Stub) -> Self {
+        Self::with_config(stub.config(), stub)
+    }
+
+    pub fn with_config(config: Config, stub: Stub) -> Self {
+        let mut harness = Harness {
+            app: App::new(config),
+            terminal: Terminal::new(TestBackend::new(100, 30)).unwrap(),
+            stub,
+        };
+        harness.wait_sync();
+        harness
+    }
+
+    /// Tick until the background sync ends.
+    pub fn wait_sync(&mut self) {
+        let start = Instant::now();
+        while self.app.syncing() {
+            assert!(start.elapsed() < Duration::from_secs(5), "sync timed out");
+            thread::sleep(Duration::from_millis(5));
+            self.app.on_tick();
+        }
+    }
+
+    /// Send the events one by one, ticking and drawing after each like the run loop.
+    ///
+    /// Commands may take two ticks, one to update from himalaya and one to run.
+    pub fn send<I: IntoIterator<Item = Event>>(&mut self, events: I) {
+        for event in events {
+            self.app.on_event(event);
+            self.app.on_tick();
+            self.app.on_tick();
+            self.render();
+        }
+    }
+
+    /// Type the text as raw input.
+    pub fn type_text(&mut self, text: &str) {
+        self.send(text.chars().map(Event::RawInput));
+    }
+
+    /// Draw the app and get the screen, one line per row.
+    pub fn render(&mut self) -> String {
+        let app = &mut self.app;
+        self.terminal.draw(|f| ui::draw(f, app)).unwrap();
+        let buffer = self.terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                screen.push_str(&buffer.get(x, y).symbol);
+            }
+            screen.push('\n');
+        }
+        screen
+    }
+
+    /// Assert the screen contains the text, printing it otherwise.
+    #[track_caller]
+    pub fn assert_shows(&mut self, text: &str) {
+        let screen = self.render();
+        assert!(
+            screen.contains(text),
+            "{:?} not shown on screen:\n{}",
+            text,
+            screen
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_hides(&mut self, text: &str) {
+        let screen = self.render();
+        assert!(
+            !screen.contains(text),
+            "{:?} shown on screen:\n{}",
+            text,
+            screen
+        );
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::fs;
+
+use common::{fixture_path, Harness, Stub};
+use himalaya_tui::{
+    app::data::Flag,
+    keymap::{Event, KeyMode},
+    mail::{BackendKind, Eml, MailBackend},
+};
+
+fn message() -> std::path::PathBuf {
+    fixture_path("message.eml")
+}
+
+#[test]
+fn single_envelope() {
+    let eml = Eml::new(message());
+    let envelopes = eml.envelopes().unwrap();
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(envelopes[0].id, 1);
+    assert_eq!(envelopes[0].subject, "Slides for Monday");
+    assert!(envelopes[0].has_attachment);
+    assert!(eml.read(2).is_err());
+    assert!(eml.add_flag(1, &Flag::Flagged).is_err());
+}
+
+#[test]
+fn open_in_the_reader() {
+    let stub = Stub::new("eml-reader");
+    let mut config = stub.config();
+    config.backend = BackendKind::Eml;
+    config.eml = Some(message());
+    let mut harness = Harness::with_config(config, stub);
+    harness.send([]);
+
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    harness.assert_shows("The agenda is at https://example.com/agenda");
+    harness.assert_shows("slides.txt");
+    assert_eq!(
+        harness.app.state.review_flags.links,
+        ["https://example.com/agenda"]
+    );
+
+    // quitting the reader quits the viewer
+    harness.send([Event::Quit]);
+    assert!(harness.app.should_quit);
+}
+
+#[test]
+fn export_from_the_reader() {
+    let stub = Stub::new("eml-export");
+    let mut config = stub.config();
+    config.backend = BackendKind::Eml;
+    config.eml = Some(message());
+    let mut harness = Harness::with_config(config, stub);
+    harness.send([]);
+    harness.send([Event::ExportMessage]);
+
+    let downloads = harness.stub.dir.join("downloads");
+    let files: Vec<_> = fs::read_dir(&downloads)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+        fs::read_to_string(&files[0]).unwrap(),
+        fs::read_to_string(message()).unwrap()
+    );
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use std::fs;
+
+use common::{fixture_path, Harness, Stub};
+use himalaya_tui::{
+    app::data::{parse_envelopes, Flag, Msg},
+    config::Config,
+    keymap::{Event, KeyMode},
+    version::Version,
+};
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(fixture_path(name)).unwrap()
+}
+
+/// Envelopes of all the fixtures, whatever the version which wrote them.
+///
+/// Attachments are only listed since 1.0.
+fn expected(attachments: bool) -> Vec<Msg> {
+    vec![
+        Msg {
+            id: 3,
+            flags: vec![Flag::Seen, Flag::Flagged],
+            subject: "Quarterly report".to_string(),
+            sender: "Alice".to_string(),
+            date: "2022-03-01 10:00".to_string(),
+            has_attachment: attachments,
+        },
+        Msg {
+            id: 2,
+            flags: vec![Flag::Custom("work".to_string())],
+            subject: "Lunch?".to_string(),
+            sender: "bob@example.com".to_string(),
+            date: "2022-02-28 12:15".to_string(),
+            has_attachment: false,
+        },
+    ]
+}
+
+#[test]
+fn parse_envelopes_of_v0_5() {
+    assert_eq!(
+        parse_envelopes(&fixture("envelopes-v0.5.json")).unwrap(),
+        expected(false)
+    );
+}
+
+#[test]
+fn parse_envelopes_of_v0_6() {
+    assert_eq!(
+        parse_envelopes(&fixture("envelopes-v0.6.json")).unwrap(),
+        expected(false)
+    );
+}
+
+#[test]
+fn parse_envelopes_of_v1_0() {
+    assert_eq!(
+        parse_envelopes(&fixture("envelopes-v1.0.json")).unwrap(),
+        expected(true)
+    );
+}
+
+#[test]
+fn cached_envelopes_round_trip() {
+    let cached = serde_json::to_string(&expected(true)).unwrap();
+    assert_eq!(parse_envelopes(&cached).unwrap(), expected(true));
+}
+
+#[test]
+fn reject_invalid_id() {
+    let output = r#"[{"id":"abc","flags":[],"subject":"","from":"","date":""}]"#;
+    assert!(parse_envelopes(output).is_err());
+}
+
+#[test]
+fn parse_version() {
+    assert_eq!(
+        Version::parse("himalaya 0.5.10"),
+        Some(Version::new(0, 5, 10))
+    );
+    assert_eq!(
+        Version::parse("himalaya v1.0.0 +imap +smtp\nbuild linux gnu"),
+        Some(Version::new(1, 0, 0))
+    );
+    assert_eq!(
+        Version::parse("himalaya 1.0.0-beta.4"),
+        Some(Version::new(1, 0, 0))
+    );
+    assert_eq!(Version::parse("himalaya"), None);
+}
+
+#[test]
+fn list_with_subcommand_of_version() {
+    assert_eq!(Version::new(0, 5, 10).list_args()[0], "list");
+    assert_eq!(Version::new(1, 0, 0).list_args()[..2], ["envelope", "list"]);
+    assert_eq!(Version::LEGACY.folders_args(), ["mailboxes"]);
+    assert_eq!(Version::new(1, 0, 0).folders_args(), ["folder", "list"]);
+    assert_eq!(Version::new(1, 0, 0).send_args(), ["message", "send"]);
+}
+
+#[test]
+fn detect_version_of_himalaya() {
+    let stub = Stub::new("version")
+        .output("--version", "himalaya v1.0.0 +imap +smtp\n")
+        .fixture("envelope", "envelopes-v1.0.json");
+    let mut harness = Harness::start(stub);
+    assert_eq!(harness.app.version, Some(Version::new(1, 0, 0)));
+    assert!(harness.stub.calls().contains("envelope list -s 0"));
+    assert_eq!(harness.app.emails, expected(true));
+    harness.assert_shows("Quarterly report");
+    harness.assert_shows("📎");
+}
+
+#[test]
+fn unknown_version_uses_legacy_list() {
+    let stub = Stub::new("legacy").fixture("list", "envelopes-v0.5.json");
+    let harness = Harness::start(stub);
+    assert_eq!(harness.app.version, None);
+    assert_eq!(harness.app.emails, expected(false));
+    assert!(!harness.app.mail.lists_attachments());
+}
+
+/// Stub of himalaya 1.0 listing the envelopes of the v1 fixture.
+fn v1(name: &str) -> Stub {
+    Stub::new(name)
+        .output("--version", "himalaya v1.0.0 +imap +smtp\n")
+        .fixture("envelope", "envelopes-v1.0.json")
+}
+
+#[test]
+fn read_with_subcommand_of_v1_0() {
+    let raw = "From: Alice <alice@example.com>\r\nSubject: Quarterly report\r\n\r\nNumbers\r\n";
+    let mut harness = Harness::start(v1("v1-read").output("message", raw));
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    assert!(harness.stub.calls().contains("message export 3 --full"));
+    harness.assert_shows("Numbers");
+}
+
+#[test]
+fn search_with_filter_of_v1_0() {
+    let mut harness = Harness::start(v1("v1-search"));
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("search from \"Alice Smith\" or seen not flagged report");
+    harness.send([Event::Submit]);
+    assert!(harness.stub.calls().contains(concat!(
+        "envelope list -s 0 from \"Alice Smith\" and ( flag seen or not flag flagged ) ",
+        "and subject report"
+    )));
+    assert_eq!(harness.app.emails, expected(true));
+}
+
+#[test]
+fn account_and_folder_after_subcommand_of_v1_0() {
+    let stub = v1("v1-scope").output("message", "Subject: Lunch?\r\n\r\nPizza\r\n");
+    let config = Config {
+        himalaya_args: vec!["--account".to_string(), "work".to_string()],
+        ..stub.config()
+    };
+    let mut harness = Harness::with_config(config, stub);
+    assert!(harness
+        .stub
+        .calls()
+        .contains("envelope list -s 0 --account work\n"));
+
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("folder Archive");
+    harness.send([Event::Submit]);
+    harness.wait_sync();
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    let calls = harness.stub.calls();
+    assert!(calls.contains("envelope list -s 0 --account work --folder Archive\n"));
+    assert!(calls.contains("message export --account work --folder Archive 3 --full\n"));
+    assert!(!calls.contains("--mailbox"));
+}
+
+#[test]
+fn account_and_folder_before_legacy_subcommand() {
+    let stub = Stub::new("legacy-scope").fixture("list", "envelopes-v0.5.json");
+    let config = Config {
+        himalaya_args: vec!["-a".to_string(), "work".to_string()],
+        folder: Some("Archive".to_string()),
+        ..stub.config()
+    };
+    let harness = Harness::with_config(config, stub);
+    assert!(harness
+        .stub
+        .calls()
+        .contains("--account work --mailbox Archive list -s 0\n"));
+}
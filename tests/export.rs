@@ -0,0 +1,153 @@
+mod common;
+
+use std::fs;
+
+use common::{envelope, fixture_path, Harness, Stub};
+use himalaya_tui::{
+    export::{export, file_name, Format},
+    keymap::{Event, KeyMode},
+    mail::{split_mbox, BackendKind},
+};
+
+#[test]
+fn name_files_from_template() {
+    let msg = envelope(7, "Re: Slides for Monday!", "Alice");
+    assert_eq!(
+        file_name("{date}-{subject}", &msg, "eml"),
+        "2022-03-04-re-slides-for-monday.eml"
+    );
+    assert_eq!(file_name("{sender}/{id}", &msg, "md"), "alice/7.md");
+    assert_eq!(
+        file_name("{subject}", &envelope(8, "???", "Alice"), "txt"),
+        "8.txt"
+    );
+}
+
+#[test]
+fn append_to_mbox() {
+    let stub = Stub::new("export-mbox");
+    let raw = fs::read_to_string(fixture_path("message.eml")).unwrap();
+    let quoted = "From: bob@example.com\nSubject: Quote\n\nFrom now on\n>From here\n";
+    let path = stub.dir.join("out.mbox");
+    let messages = [
+        (envelope(1, "Slides", "Alice"), raw.clone()),
+        (envelope(2, "Quote", "Alice"), quoted.to_string()),
+    ];
+
+    let paths = export(Format::Mbox, path.to_str().unwrap(), "{subject}", &messages).unwrap();
+    assert_eq!(paths, std::slice::from_ref(&path));
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("From alice@example.com Fri Mar  4 16:30:00 2022\n"));
+    assert!(content.contains("\n>From now on\n>>From here\n"));
+
+    let split = split_mbox(content.as_bytes());
+    assert_eq!(split.len(), 2);
+    assert_eq!(
+        String::from_utf8_lossy(&split[0]),
+        raw.replace("\r\n", "\n")
+    );
+    assert_eq!(String::from_utf8_lossy(&split[1]), quoted);
+}
+
+#[test]
+fn render_text_and_markdown() {
+    let stub = Stub::new("export-text");
+    let raw = fs::read_to_string(fixture_path("message.eml")).unwrap();
+    let messages = [(envelope(1, "Slides for Monday", "Alice"), raw)];
+    let dir = format!("{}/", stub.dir.display());
+
+    let text = export(Format::Text, &dir, "{date}-{subject}", &messages).unwrap();
+    assert_eq!(text, [stub.dir.join("2022-03-04-slides-for-monday.txt")]);
+    let text = fs::read_to_string(&text[0]).unwrap();
+    assert!(text.starts_with("From: Alice <alice@example.com>\nTo: bob@example.com\n"));
+    assert!(text.contains("\n\nThe agenda is at https://example.com/agenda, slides attached.\n"));
+    assert!(text.ends_with("Attachments:\n- slides.txt (9 B)\n"));
+
+    let markdown = export(Format::Markdown, &dir, "{id}", &messages).unwrap();
+    let markdown = fs::read_to_string(&markdown[0]).unwrap();
+    assert!(markdown.starts_with("# Slides for Monday\n\n- **From:** Alice <alice@example.com>\n"));
+    assert!(markdown.contains("## Attachments\n\n- slides.txt"));
+}
+
+#[test]
+fn export_into_subdirectories_of_template() {
+    let stub = Stub::new("export-subdirectories");
+    let raw = fs::read_to_string(fixture_path("message.eml")).unwrap();
+    let messages = [
+        (envelope(1, "Slides", "Alice"), raw.clone()),
+        (envelope(2, "Agenda", "Alice"), raw),
+    ];
+    let dir = format!("{}/", stub.dir.display());
+
+    let paths = export(Format::Eml, &dir, "{sender}/{id}", &messages).unwrap();
+    assert_eq!(
+        paths,
+        [
+            stub.dir.join("alice").join("1.eml"),
+            stub.dir.join("alice").join("2.eml")
+        ]
+    );
+    assert!(paths.iter().all(|path| path.exists()));
+
+    // existing files are kept
+    let again = export(Format::Eml, &dir, "{sender}/{id}", &messages[..1]).unwrap();
+    assert_eq!(again, [stub.dir.join("alice").join("1-1.eml")]);
+}
+
+#[test]
+fn marks_follow_messages_across_syncs() {
+    let stub = Stub::new("export-marks").fixture("list", "list.json");
+    let mut harness = Harness::start(stub);
+    harness.send([Event::SelectNextMsg, Event::ToggleMark]);
+    harness.app.sync();
+    harness.wait_sync();
+    assert_eq!(harness.app.marked.len(), 1);
+
+    // the id of the marked message is given to a new one
+    let list = r#"{"response": [
+        {"id": 2, "flags": [], "subject": "Fresh news", "sender": "carol@example.com",
+         "date": "2022-03-02 08:00:00"},
+        {"id": 1, "flags": ["Seen"], "subject": "Welcome aboard", "sender": "bob@example.com",
+         "date": "2022-02-28 09:30:00"}
+    ]}"#;
+    fs::write(harness.stub.dir.join("list.json"), list).unwrap();
+    harness.app.sync();
+    harness.wait_sync();
+    assert!(harness.app.marked.is_empty());
+}
+
+#[test]
+fn export_marked_messages_from_the_app() {
+    let stub = Stub::new("export-app");
+    let mut config = stub.config();
+    config.backend = BackendKind::Mbox;
+    config.mbox = Some(fixture_path("archive.mbox"));
+    let out = stub.dir.join("out");
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.send([Event::SelectNextMsg, Event::ToggleMark, Event::ToggleMark]);
+    let marked: Vec<usize> = harness.app.marked.iter().map(|msg| msg.id).collect();
+    assert_eq!(marked, [2, 1]);
+
+    // without a path, the command line asks for one
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("export eml");
+    harness.send([Event::Submit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Insert);
+    assert!(harness.app.command_input.text().starts_with("export eml "));
+
+    harness.send([Event::DeleteToStart]);
+    harness.type_text(&format!("export eml {}/", out.display()));
+    harness.send([Event::Submit]);
+    let mut files: Vec<String> = fs::read_dir(&out)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        ["2022-03-01-lunch.eml", "2022-03-01-quarterly-report.eml"]
+    );
+    assert!(harness.app.marked.is_empty());
+    harness.assert_shows("Exported 2 files");
+}
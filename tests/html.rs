@@ -0,0 +1,68 @@
+use himalaya_tui::html::{decode_entities, render};
+
+/// Render the document and get the text of its lines, with its links.
+fn text(html: &str, label_links: bool) -> (Vec<String>, Vec<String>) {
+    let mut links = Vec::new();
+    let lines = render(html, &mut links, label_links)
+        .iter()
+        .map(|spans| spans.0.iter().map(|span| span.content.as_ref()).collect())
+        .collect();
+    (lines, links)
+}
+
+#[test]
+fn skip_invisible_elements() {
+    let (lines, _) = text(
+        "<html><head><title>Hidden</title><style>p { color: red }</style></head>\
+         <body><script>alert('hidden')</script><p>Shown</p></body></html>",
+        false,
+    );
+    assert_eq!(lines, ["Shown"]);
+}
+
+#[test]
+fn unclosed_invisible_elements_hide_nothing_else() {
+    let (lines, _) = text("<head><title>Hidden</title><body><p>Shown</p>", false);
+    assert_eq!(lines, ["Shown"]);
+    let (lines, _) = text("<p>Before</p><script><p>After</p>", false);
+    assert_eq!(lines, ["Before", "", "After"]);
+    let (lines, _) = text("<style><p>Still shown</p>", false);
+    assert_eq!(lines, ["Still shown"]);
+}
+
+#[test]
+fn decode_character_references() {
+    assert_eq!(
+        decode_entities("Tom &amp; Jerry &lt;3 &#233;t&#xE9; &hellip;"),
+        "Tom & Jerry <3 été …"
+    );
+    // unknown or unterminated references are kept as is
+    assert_eq!(decode_entities("&bogus; a & b &amp"), "&bogus; a & b &amp");
+    let (lines, _) = text("<p>caf&eacute; &quot;ok&quot;&nbsp;go</p>", false);
+    assert_eq!(lines, ["caf&eacute; \"ok\" go"]);
+}
+
+#[test]
+fn render_lists() {
+    let (lines, _) = text(
+        "<ul><li>One</li><li>Two<ol><li>First</li><li>Second</li></ol></li></ul>",
+        false,
+    );
+    assert_eq!(lines, ["• One", "• Two", "  1. First", "  2. Second"]);
+}
+
+#[test]
+fn collect_and_label_links() {
+    let html = "<p>See <a href=\"https://example.com/a\">the docs</a> and \
+                <a href='#top'>top</a> or <a HREF=https://example.com/b>b</a>.</p>";
+    let (lines, links) = text(html, false);
+    assert_eq!(
+        lines,
+        ["See the docs <https://example.com/a> and top or b <https://example.com/b>."]
+    );
+    assert_eq!(links, ["https://example.com/a", "https://example.com/b"]);
+
+    let (lines, _) = text(html, true);
+    assert!(lines[0].contains("<https://example.com/a> [1]"));
+    assert!(lines[0].contains("<https://example.com/b> [2]"));
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{Harness, Stub};
+use himalaya_tui::{
+    app::data::Flag,
+    keymap::{Event, KeyMode},
+    mail::{BackendKind, MailBackend, Maildir},
+};
+
+/// Create a Maildir++ account with two messages in INBOX and an empty Archive.
+fn account(stub: &Stub) -> std::path::PathBuf {
+    let root = stub.dir.join("mail");
+    for dir in [&root, &root.join(".Archive"), &root.join(".Lists.Rust")] {
+        for sub in ["cur", "new", "tmp"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+    }
+    fs::write(
+        root.join("cur").join("1646128800.1.host:2,FS"),
+        "From: Alice <alice@example.com>\r\n\
+         Subject: Quarterly report\r\n\
+         Date: Tue, 01 Mar 2022 10:00:00 +0000\r\n\
+         Content-Type: multipart/mixed; boundary=b\r\n\
+         \r\n\
+         --b\r\n\
+         Content-Type: text/plain\r\n\
+         \r\n\
+         Numbers are up.\r\n\
+         --b--\r\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("new").join("1646140000.2.host"),
+        "From: bob@example.com\r\n\
+         Subject: Lunch?\r\n\
+         Date: Tue, 01 Mar 2022 13:06:40 +0000\r\n\
+         \r\n\
+         Pizza at noon?\r\n",
+    )
+    .unwrap();
+    root
+}
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn list_envelopes_from_headers() {
+    let stub = Stub::new("maildir-list");
+    let maildir = Maildir::new(account(&stub), "INBOX");
+    let envelopes = maildir.envelopes().unwrap();
+
+    assert_eq!(envelopes.len(), 2);
+    assert_eq!(envelopes[0].id, 2);
+    assert_eq!(envelopes[0].sender, "bob@example.com");
+    assert_eq!(envelopes[0].flags, vec![Flag::Recent]);
+    assert_eq!(envelopes[1].subject, "Quarterly report");
+    assert_eq!(envelopes[1].sender, "Alice");
+    assert_eq!(envelopes[1].date, "2022-03-01 10:00");
+    assert_eq!(envelopes[1].flags, vec![Flag::Flagged, Flag::Seen]);
+    assert!(envelopes[1].has_attachment);
+}
+
+#[test]
+fn list_folders_of_both_layouts() {
+    let stub = Stub::new("maildir-folders");
+    let root = account(&stub);
+    for sub in ["cur", "new", "tmp"] {
+        fs::create_dir_all(root.join("Sent").join(sub)).unwrap();
+    }
+    let maildir = Maildir::new(root, "INBOX");
+    assert_eq!(
+        maildir.folders().unwrap(),
+        ["Archive", "INBOX", "Lists/Rust", "Sent"]
+    );
+}
+
+#[test]
+fn flag_by_renaming() {
+    let stub = Stub::new("maildir-flag");
+    let root = account(&stub);
+    let maildir = Maildir::new(root.clone(), "INBOX");
+
+    maildir.add_flag(2, &Flag::Seen).unwrap();
+    maildir.remove_flag(1, &Flag::Flagged).unwrap();
+    assert!(files(&root.join("new")).is_empty());
+    assert_eq!(
+        files(&root.join("cur")),
+        ["1646128800.1.host:2,S", "1646140000.2.host:2,S"]
+    );
+    assert!(maildir
+        .add_flag(1, &Flag::Custom("work".to_string()))
+        .is_err());
+}
+
+#[test]
+fn move_to_folder() {
+    let stub = Stub::new("maildir-move");
+    let root = account(&stub);
+    let maildir = Maildir::new(root.clone(), "INBOX");
+
+    maildir.move_to(1, "Archive").unwrap();
+    assert_eq!(
+        files(&root.join(".Archive").join("cur")),
+        ["1646128800.1.host:2,FS"]
+    );
+    assert_eq!(maildir.envelopes().unwrap().len(), 1);
+    assert!(maildir.move_to(1, "Nowhere").is_err());
+}
+
+#[test]
+fn search_with_keywords() {
+    let stub = Stub::new("maildir-search");
+    let maildir = Maildir::new(account(&stub), "INBOX");
+    let search = |query: &str| -> Vec<usize> {
+        let words: Vec<String> = query.split(' ').map(|w| w.to_string()).collect();
+        maildir
+            .search(&words)
+            .unwrap()
+            .iter()
+            .map(|msg| msg.id)
+            .collect()
+    };
+
+    assert_eq!(search("from alice"), [1]);
+    assert_eq!(search("not seen"), [2]);
+    assert_eq!(search("lunch"), [2]);
+    assert_eq!(search("flagged report"), [1]);
+    assert_eq!(search("unseen"), [2]);
+    assert_eq!(search("unflagged lunch"), [2]);
+    assert_eq!(search("subject \"quarterly report\""), [1]);
+    assert!(search("subject \"report quarterly\"").is_empty());
+}
+
+#[test]
+fn read_and_flag_from_the_app() {
+    let stub = Stub::new("maildir-app");
+    let root = account(&stub);
+    let mut config = stub.config();
+    config.backend = BackendKind::Maildir;
+    config.maildir = Some(root.clone());
+    let mut harness = Harness::with_config(config, stub);
+
+    // himalaya is never run
+    assert_eq!(harness.stub.calls(), "");
+    harness.assert_shows("Lunch?");
+    harness.send([Event::SelectNextMsg, Event::ToggleSeen]);
+    harness.wait_sync();
+    assert_eq!(files(&root.join("cur")).len(), 2);
+
+    harness.send([Event::ReviewMsg]);
+    harness.assert_shows("Pizza at noon?");
+}
+
+#[test]
+fn reading_marks_seen() {
+    let stub = Stub::new("maildir-seen");
+    let root = account(&stub);
+    let mut config = stub.config();
+    config.backend = BackendKind::Maildir;
+    config.maildir = Some(root.clone());
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    harness.assert_shows("Pizza at noon?");
+    assert!(files(&root.join("new")).is_empty());
+    assert_eq!(files(&root.join("cur"))[1], "1646140000.2.host:2,S");
+    assert!(harness.app.emails[0].flags.contains(&Flag::Seen));
+}
+
+#[test]
+fn failed_tag_does_not_sync() {
+    let stub = Stub::new("maildir-tag");
+    let mut config = stub.config();
+    config.backend = BackendKind::Maildir;
+    config.maildir = Some(account(&stub));
+    let mut harness = Harness::with_config(config, stub);
+    let synced = harness.app.last_sync;
+
+    harness.send([Event::SelectNextMsg, Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("tag +work");
+    harness.send([Event::Submit]);
+    harness.wait_sync();
+    harness.assert_shows("Cannot tag message");
+    assert_eq!(harness.app.last_sync, synced);
+}
+
+#[test]
+fn reply_from_headers_of_the_message() {
+    // himalaya would answer with a message of another account
+    let stub = Stub::new("maildir-reply").output(
+        "template",
+        r#"{"response": "To: someone@example.com\nSubject: Re: Other\n\n"}"#,
+    );
+    let mut config = stub.config();
+    config.backend = BackendKind::Maildir;
+    config.maildir = Some(account(&stub));
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    harness.send([Event::StartVisual, Event::QuoteReply]);
+    assert!(harness.app.interactive.take().is_some());
+    let draft = harness.app.draft.as_ref().unwrap();
+    let content = fs::read_to_string(&draft.path).unwrap();
+    assert!(content.starts_with("To: bob@example.com\nSubject: Re: Lunch?\n"));
+    assert_eq!(harness.stub.calls(), "");
+}
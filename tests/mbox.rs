@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+
+use common::{fixture_path, Harness, Stub};
+use himalaya_tui::{
+    app::data::Flag,
+    keymap::Event,
+    mail::{split_mbox, BackendKind, MailBackend, Mbox},
+};
+
+fn archive() -> std::path::PathBuf {
+    fixture_path("archive.mbox")
+}
+
+#[test]
+fn split_and_unquote_messages() {
+    let messages = split_mbox(&fs::read(archive()).unwrap());
+    assert_eq!(messages.len(), 2);
+    let first = String::from_utf8_lossy(&messages[0]);
+    assert!(first.starts_with("From: Alice"));
+    assert!(first.ends_with("From the desk of Alice.\n>From here on, quoted.\n"));
+    assert_eq!(
+        String::from_utf8_lossy(&messages[1]),
+        concat!(
+            "From: bob@example.com\nSubject: Lunch?\n",
+            "Date: Tue, 01 Mar 2022 13:06:40 +0000\n\nPizza at noon?\n"
+        )
+    );
+}
+
+#[test]
+fn list_envelopes_read_only() {
+    let mbox = Mbox::new(archive());
+    let envelopes = mbox.envelopes().unwrap();
+    assert_eq!(envelopes.len(), 2);
+    assert_eq!(envelopes[0].id, 2);
+    assert_eq!(envelopes[0].flags, vec![]);
+    assert_eq!(envelopes[1].sender, "Alice");
+    assert_eq!(envelopes[1].flags, vec![Flag::Seen, Flag::Flagged]);
+
+    assert!(mbox.read(2).unwrap().contains("Pizza at noon?"));
+    assert!(mbox.read(3).is_err());
+    assert!(mbox.add_flag(1, &Flag::Seen).is_err());
+    assert!(mbox.move_to(1, "Archive").is_err());
+}
+
+#[test]
+fn read_and_export_from_the_app() {
+    let stub = Stub::new("mbox-app");
+    let mut config = stub.config();
+    config.backend = BackendKind::Mbox;
+    config.mbox = Some(archive());
+    let downloads = config.downloads_dir.clone();
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.assert_shows("archive.mbox");
+    harness.assert_shows("Lunch?");
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    harness.assert_shows("Pizza at noon?");
+
+    harness.send([Event::ExportMessage]);
+    let path = downloads.join("2022-03-01-lunch.eml");
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains("Pizza at noon?"));
+    harness.assert_shows("Saved");
+
+    // no message is overwritten
+    harness.send([Event::ExportMessage]);
+    assert!(downloads.join("2022-03-01-lunch-1.eml").exists());
+}
+
+#[test]
+fn files_of_the_same_name_are_not_mixed() {
+    let stub = Stub::new("mbox-same-name");
+    let other = stub.dir.join("other").join("archive.mbox");
+    fs::create_dir_all(other.parent().unwrap()).unwrap();
+    fs::write(
+        &other,
+        "From carol@example.com Wed Mar  2 08:00:00 2022\n\
+         From: carol@example.com\n\
+         Subject: Minutes\n\
+         \n\
+         Notes of the meeting.\n",
+    )
+    .unwrap();
+
+    let mut config = stub.config();
+    config.backend = BackendKind::Mbox;
+    config.mbox = Some(archive());
+    let first = Harness::with_config(config.clone(), Stub::new("mbox-first"));
+    assert_eq!(first.app.emails.len(), 2);
+
+    config.mbox = Some(other);
+    let mut second = Harness::with_config(config, stub);
+    second.assert_shows("Minutes");
+    second.assert_hides("Lunch?");
+    assert_eq!(second.app.new_mail, 0);
+    assert!(!second.stub.dir.join("cache").exists());
+
+    second.app.sync();
+    second.wait_sync();
+    second.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    second.assert_shows("Notes of the meeting.");
+    assert_eq!(second.app.new_mail, 0);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::{
+    fs, thread,
+    time::{Duration, Instant},
+};
+
+use common::{envelope, Stub};
+use himalaya_tui::{
+    app::data::Msg,
+    notify::{NotifyConfig, MAX_COMMANDS},
+};
+
+fn msg(id: usize, sender: &str) -> Msg {
+    envelope(id, &format!("Message {}", id), sender)
+}
+
+/// Configuration appending the environment of each run of the command to a file.
+fn config(stub: &Stub) -> NotifyConfig {
+    let log = stub.dir.join("notified");
+    NotifyConfig {
+        command: vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!(
+                "echo \"$HIMALAYA_COUNT|$HIMALAYA_SENDER|$HIMALAYA_SUBJECT\" >> {}",
+                log.display()
+            ),
+        ],
+        ..NotifyConfig::default()
+    }
+}
+
+/// Wait for the given number of runs of the command, and get their lines sorted.
+fn runs(stub: &Stub, count: usize) -> Vec<String> {
+    let start = Instant::now();
+    loop {
+        let log = fs::read_to_string(stub.dir.join("notified")).unwrap_or_default();
+        let mut lines: Vec<String> = log.lines().map(|line| line.to_string()).collect();
+        if lines.len() >= count || start.elapsed() > Duration::from_secs(5) {
+            lines.sort();
+            return lines;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn run_command_for_each_message() {
+    let stub = Stub::new("notify-each");
+    let (a, b) = (msg(1, "alice"), msg(2, "bob"));
+    config(&stub).notify("INBOX", &[&a, &b]).unwrap();
+    assert_eq!(runs(&stub, 2), ["1|alice|Message 1", "1|bob|Message 2"]);
+}
+
+#[test]
+fn summarize_messages_past_the_cap() {
+    let stub = Stub::new("notify-cap");
+    let msgs: Vec<Msg> = (1..=10)
+        .map(|id| msg(id, if id % 2 == 0 { "alice" } else { "bob" }))
+        .collect();
+    let refs: Vec<&Msg> = msgs.iter().collect();
+    config(&stub).notify("INBOX", &refs).unwrap();
+
+    let lines = runs(&stub, MAX_COMMANDS);
+    // give extra processes a chance to show up
+    thread::sleep(Duration::from_millis(100));
+    let log = fs::read_to_string(stub.dir.join("notified")).unwrap();
+    assert_eq!(log.lines().count(), MAX_COMMANDS);
+    assert_eq!(
+        lines,
+        [
+            "1|alice|Message 2",
+            "1|bob|Message 1",
+            "8|bob, alice|8 more new mails"
+        ]
+    );
+}
+
+#[test]
+fn rules_filter_messages() {
+    let stub = Stub::new("notify-rules");
+    let config = NotifyConfig {
+        senders: vec!["ALICE".to_string()],
+        folders: vec!["inbox".to_string()],
+        ..config(&stub)
+    };
+    let (a, b) = (msg(1, "alice@example.com"), msg(2, "bob"));
+    assert!(config.matches("INBOX", &a));
+    assert!(!config.matches("INBOX", &b));
+    assert!(!config.matches("Archive", &a));
+
+    config.notify("INBOX", &[&a, &b]).unwrap();
+    assert_eq!(runs(&stub, 1), ["1|alice@example.com|Message 1"]);
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt};
+
+use common::{fixture_path, Harness, Stub};
+use himalaya_tui::{
+    app::data::Flag,
+    keymap::{Event, KeyMode},
+    mail::{BackendKind, MailBackend, Notmuch},
+};
+
+/// Stub of notmuch logging its arguments to `calls`, answering `show` with `show.json`,
+/// or `raw.eml` for raw messages, and `search` with `tags.json`.
+const STUB: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+echo "$@" >> "$dir/calls"
+case "$1 $2" in
+    "show --format=raw") cat "$dir/raw.eml" ;;
+    "show "*) cat "$dir/show.json" ;;
+    "search "*) cat "$dir/tags.json" ;;
+esac
+"#;
+
+fn notmuch(name: &str) -> Stub {
+    let stub = Stub::new(name);
+    let script = stub.dir.join("notmuch");
+    fs::write(&script, STUB).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::copy(
+        fixture_path("notmuch-show.json"),
+        stub.dir.join("show.json"),
+    )
+    .unwrap();
+    fs::write(
+        stub.dir.join("tags.json"),
+        r#"["attachment", "inbox", "replied", "unread", "work"]"#,
+    )
+    .unwrap();
+    fs::write(
+        stub.dir.join("raw.eml"),
+        "From: bob@example.com\r\nSubject: Lunch?\r\n\r\nPizza at noon?\r\n",
+    )
+    .unwrap();
+    stub
+}
+
+#[test]
+fn list_matching_messages_with_tags() {
+    let stub = notmuch("notmuch-list");
+    let backend = Notmuch::new(stub.dir.join("notmuch"), "INBOX");
+    let envelopes = backend.envelopes().unwrap();
+
+    assert_eq!(envelopes.len(), 2);
+    assert_eq!(envelopes[0].subject, "Lunch?");
+    assert_eq!(envelopes[0].flags, vec![Flag::Custom("inbox".to_string())]);
+    assert_eq!(envelopes[1].sender, "Alice");
+    assert_eq!(envelopes[1].date, "2022-03-01 10:00");
+    assert_eq!(
+        envelopes[1].flags,
+        vec![
+            Flag::Seen,
+            Flag::Flagged,
+            Flag::Custom("inbox".to_string()),
+            Flag::Custom("work".to_string())
+        ]
+    );
+    assert!(envelopes[1].has_attachment);
+    assert!(stub.calls().contains("--entire-thread=false tag:inbox"));
+
+    // ids stay the same across listings
+    let ids: Vec<usize> = envelopes.iter().map(|msg| msg.id).collect();
+    let again: Vec<usize> = backend
+        .envelopes()
+        .unwrap()
+        .iter()
+        .map(|msg| msg.id)
+        .collect();
+    assert_eq!(ids, again);
+}
+
+#[test]
+fn flags_as_tags() {
+    let stub = notmuch("notmuch-tag");
+    let backend = Notmuch::new(stub.dir.join("notmuch"), "INBOX");
+    let lunch = backend.envelopes().unwrap()[0].id;
+
+    backend.add_flag(lunch, &Flag::Seen).unwrap();
+    backend
+        .remove_flag(lunch, &Flag::Custom("work".to_string()))
+        .unwrap();
+    backend.move_to(lunch, "archive").unwrap();
+    let calls = stub.calls();
+    assert!(calls.contains("tag -unread -- id:\"lunch@example.com\""));
+    assert!(calls.contains("tag -work -- id:\"lunch@example.com\""));
+    assert!(calls.contains("tag -inbox +archive -- id:\"lunch@example.com\""));
+    assert!(backend.add_flag(lunch, &Flag::Recent).is_err());
+    assert!(backend.add_flag(99, &Flag::Seen).is_err());
+}
+
+#[test]
+fn search_and_tag_from_the_app() {
+    let stub = notmuch("notmuch-app");
+    let mut config = stub.config();
+    config.backend = BackendKind::Notmuch;
+    config.notmuch = stub.dir.join("notmuch");
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.assert_shows("Quarterly report");
+
+    harness.send([Event::SwitchMode(KeyMode::Insert)]);
+    harness.type_text("search from:alice and tag:work");
+    harness.send([Event::Submit]);
+    assert!(harness
+        .stub
+        .calls()
+        .contains("--entire-thread=false from:alice and tag:work"));
+
+    harness.send([Event::SelectNextMsg]);
+    // the command line still holds the search
+    harness.send([Event::SwitchMode(KeyMode::Insert), Event::DeleteToStart]);
+    harness.type_text("tag +todo -inbox");
+    harness.send([Event::Submit]);
+    harness.wait_sync();
+    let calls = harness.stub.calls();
+    assert!(calls.contains("tag +todo -- id:"));
+    assert!(calls.contains("tag -inbox -- id:"));
+}
+
+#[test]
+fn read_raw_message() {
+    let stub = notmuch("notmuch-read");
+    let backend = Notmuch::new(stub.dir.join("notmuch"), "INBOX");
+    let lunch = backend.envelopes().unwrap()[0].id;
+    assert!(backend.read(lunch).unwrap().contains("Pizza at noon?"));
+    assert_eq!(backend.folders().unwrap(), ["inbox", "work"]);
+}
+
+#[test]
+fn reading_removes_unread_tag() {
+    let stub = notmuch("notmuch-seen");
+    let mut config = stub.config();
+    config.backend = BackendKind::Notmuch;
+    config.notmuch = stub.dir.join("notmuch");
+    let mut harness = Harness::with_config(config, stub);
+
+    harness.send([Event::SelectNextMsg, Event::ReviewMsg]);
+    harness.assert_shows("Pizza at noon?");
+    assert!(harness
+        .stub
+        .calls()
+        .contains("tag -unread -- id:\"lunch@example.com\""));
+    assert!(harness.app.emails[0].flags.contains(&Flag::Seen));
+}
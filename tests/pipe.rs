@@ -0,0 +1,113 @@
+mod common;
+
+use std::fs;
+
+use common::{fixture_path, Harness, Stub};
+use himalaya_tui::{
+    keymap::{Event, KeyMode},
+    mail::BackendKind,
+    pipe::Pipe,
+};
+
+/// Open the message of the fixture in the reader.
+fn reader(name: &str) -> Harness {
+    let stub = Stub::new(name);
+    let mut config = stub.config();
+    config.backend = BackendKind::Eml;
+    config.eml = Some(fixture_path("message.eml"));
+    let mut harness = Harness::with_config(config, stub);
+    harness.send([]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    harness
+}
+
+fn pipe(harness: &mut Harness, command: &str) {
+    harness.send([Event::PipeMessage]);
+    assert_eq!(harness.app.command_input.text(), "pipe ");
+    harness.type_text(command);
+    harness.send([Event::Submit]);
+}
+
+#[test]
+fn parse_options() {
+    let words = |line: &str| -> Vec<String> { line.split(' ').map(|w| w.to_string()).collect() };
+    assert_eq!(
+        Pipe::parse(&words("-d -i urlview -x")),
+        Some(Pipe {
+            decoded: true,
+            interactive: true,
+            command: "urlview -x".to_string()
+        })
+    );
+    assert_eq!(
+        Pipe::parse(&words("git am -3")),
+        Some(Pipe {
+            decoded: false,
+            interactive: false,
+            command: "git am -3".to_string()
+        })
+    );
+    assert_eq!(Pipe::parse(&words("-d")), None);
+}
+
+#[test]
+fn show_output_of_raw_message() {
+    let mut harness = reader("pipe-raw");
+    pipe(&mut harness, "grep -c Content-Type");
+    assert_eq!(harness.app.keymap.mode, KeyMode::Output);
+    harness.assert_shows("| grep -c Content-Type");
+    assert_eq!(harness.app.pipe_output.as_ref().unwrap().text, "3\n");
+
+    harness.send([Event::Quit]);
+    assert_eq!(harness.app.keymap.mode, KeyMode::Review);
+    assert!(harness.app.pipe_output.is_none());
+}
+
+#[test]
+fn pipe_decoded_text() {
+    let mut harness = reader("pipe-decoded");
+    pipe(&mut harness, "-d grep agenda");
+    let output = harness.app.pipe_output.as_ref().unwrap();
+    assert!(output.success);
+    assert_eq!(
+        output.text,
+        "The agenda is at https://example.com/agenda, slides attached.\n"
+    );
+}
+
+#[test]
+fn show_failure() {
+    let mut harness = reader("pipe-failure");
+    pipe(&mut harness, "exit 3");
+    assert!(!harness.app.pipe_output.as_ref().unwrap().success);
+    harness.assert_shows("(no output)");
+}
+
+#[test]
+fn suspend_for_interactive_command() {
+    let mut harness = reader("pipe-interactive");
+    pipe(&mut harness, "-i less");
+    let command = harness.app.interactive.take().unwrap();
+    assert_eq!(command.get_program(), "sh");
+    assert_eq!(command.get_args().collect::<Vec<_>>(), ["-c", "less"]);
+}
+
+#[test]
+fn interactive_input_is_private() {
+    let stub = Stub::new("pipe-private");
+    let pipe = Pipe::parse(&["-i".to_string(), "cat".to_string()]).unwrap();
+    let mut command = pipe.interactive("secret message", &stub.dir).unwrap();
+
+    // the file is gone before the command runs, which still reads it
+    assert!(!stub.dir.join("pipe.eml").exists());
+    let output = command.output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "secret message");
+
+    // a file planted in its place is never read
+    fs::write(stub.dir.join("pipe.eml"), "planted").unwrap();
+    assert!(pipe.interactive("secret message", &stub.dir).is_err());
+    assert_eq!(
+        fs::read_to_string(stub.dir.join("pipe.eml")).unwrap(),
+        "planted"
+    );
+}
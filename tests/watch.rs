@@ -0,0 +1,35 @@
+use std::{
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use himalaya_tui::watch::{WatchEvent, Watcher};
+
+#[test]
+fn only_stdout_lines_are_changes() {
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "echo connecting >&2; echo 'new message'; echo 'idle timeout' >&2",
+    ]);
+    let mut watcher = Watcher::spawn(command).unwrap();
+
+    let start = Instant::now();
+    let mut changes = 0;
+    let exit = loop {
+        assert!(start.elapsed() < Duration::from_secs(5), "watch timed out");
+        match watcher.try_next() {
+            Some(WatchEvent::Changed) => changes += 1,
+            Some(WatchEvent::Exited(last)) => break last,
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    };
+    assert_eq!(exit, "idle timeout");
+    // the change may come after the end of stderr
+    thread::sleep(Duration::from_millis(50));
+    while let Some(WatchEvent::Changed) = watcher.try_next() {
+        changes += 1;
+    }
+    assert_eq!(changes, 1);
+}